@@ -1,22 +1,247 @@
 use crate::{
     command::Command,
-    error::{ATResult, ATVecResult, ErrAutoType, ErrType},
+    config::ConfigLocations,
+    content::Content,
+    delay::Delay,
+    error::{ATResult, ATVecResult, ErrAutoType, ErrType, Location},
+    format::Format,
+    migration::{self, Version},
+    schema,
+    segment::Segment,
     sequence::Sequences,
+    typer::CommandTemplate,
+};
+use clap::ValueEnum;
+use indexmap::IndexMap;
+use schemars::{schema::RootSchema, schema_for, JsonSchema};
+use serde::{Deserialize, Serialize, Serializer};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
 };
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, str::FromStr};
 
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
 struct Combination {
+    /// Names of sequences and combinations separated by white spaces,
+    /// each optionally followed by number of repetitions like `3` or `2..5`.
+    #[schemars(regex = "schema::COMBINATION_PATTERN")]
     sequence: String,
-    delay: Option<usize>,
+    /// Delay between key strokes in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    delay: Option<Delay>,
+    /// Time to wait in milliseconds before typing starts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_delay: Option<usize>,
+    /// Hide the combination in listings and cheat sheets.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    hidden: bool,
+}
+
+/// Kind of entry in configuration.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Sequence,
+    Combination,
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Sequence => write!(f, "sequence"),
+            Kind::Combination => write!(f, "combination"),
+        }
+    }
+}
+
+impl Kind {
+    /// Returns title of section with entries of the kind.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Kind::Sequence => "Sequences",
+            Kind::Combination => "Combinations",
+        }
+    }
+}
+
+/// Order of listed sequences and combinations.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ListOrder {
+    /// Order of definition in configuration, sequences first.
+    Config,
+    /// Alphabetical order by name.
+    #[default]
+    Name,
+    /// Sequences and combinations separately, each sorted by name.
+    Kind,
+}
+
+/// Configuration file included in another one. Path is relative
+/// to the including file and it can be a glob pattern. If namespace
+/// is set, it is prepended to all keys of the included file.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+enum Include {
+    Path(String),
+    Namespaced {
+        /// Path or glob pattern relative to the including file.
+        path: String,
+        /// Prefix prepended to all keys of the included file.
+        #[schemars(regex = "schema::NAME_PATTERN")]
+        namespace: Option<String>,
+    },
+}
+
+impl Include {
+    fn path(&self) -> &str {
+        match self {
+            Include::Path(path) | Include::Namespaced { path, .. } => path,
+        }
+    }
+
+    fn namespace(&self) -> Option<&str> {
+        match self {
+            Include::Path(_) => None,
+            Include::Namespaced { namespace, .. } => namespace.as_deref(),
+        }
+    }
+
+    /// Returns files matching the include relative to directory `dir`.
+    /// Path without glob characters is returned even if it does not exist.
+    fn resolve(&self, dir: &Path) -> ATResult<Vec<PathBuf>> {
+        let path = dir.join(ConfigLocations::from_env().expand(self.path()));
+        let pattern = path.to_string_lossy();
+        if !pattern.contains(['*', '?', '[']) {
+            return Ok(vec![path]);
+        }
+        let invalid = |message: String| {
+            ErrAutoType::new_with_message(ErrType::InvalidInclude(pattern.to_string()), message)
+        };
+        glob::glob(&pattern)
+            .map_err(|e| invalid(e.to_string()))?
+            .map(|entry| entry.map_err(|e| invalid(e.to_string())))
+            .collect()
+    }
+}
+
+/// Sequences and combinations of a profile, which override or extend
+/// the base configuration when the profile is selected.
+#[derive(Deserialize, Serialize, JsonSchema, Default, Clone, Debug, PartialEq, Eq)]
+struct Profile {
+    /// Combinations added or replaced by the profile.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    #[schemars(schema_with = "schema::names_map::<Combination>")]
+    combinations: IndexMap<String, Combination>,
+    /// Sequences added or replaced by the profile.
+    #[serde(default, skip_serializing_if = "Sequences::is_empty")]
+    sequences: Sequences,
+    /// Configuration files where entries of the profile are defined. They
+    /// are kept apart from sources of the base configuration, because
+    /// profiles may override entries of included files.
+    #[serde(skip)]
+    sources: HashMap<String, PathBuf>,
+}
+
+impl Profile {
+    /// Returns keys of all sequences and combinations of the profile.
+    fn keys(&self) -> impl Iterator<Item = &String> {
+        self.sequences.get_keys().chain(self.combinations.keys())
+    }
+
+    /// Merge `other` over `self` same as [`Combinations::merge()`].
+    fn merge(&mut self, other: Profile) {
+        other.sequences.get_keys().for_each(|key| {
+            self.combinations.shift_remove(key);
+        });
+        other.combinations.keys().for_each(|key| {
+            self.sequences.remove(key);
+        });
+        self.sequences.merge(other.sequences);
+        self.combinations.extend(other.combinations);
+        self.sources.extend(other.sources);
+    }
+
+    /// Rename entry `key` to `new_key` if it is in the profile
+    /// and rewrite all references to it.
+    fn rename(&mut self, key: &str, new_key: &str) {
+        if let Some((index, _, combination)) = self.combinations.shift_remove_full(key) {
+            self.combinations
+                .shift_insert(index, String::from(new_key), combination);
+        }
+        self.sequences.rename(key, new_key);
+        if let Some(source) = self.sources.remove(key) {
+            self.sources.insert(String::from(new_key), source);
+        }
+        rewrite_combinations(&mut self.combinations, |name| {
+            (name == key).then(|| String::from(new_key))
+        });
+    }
+}
+
+/// Rewrite references in all `combinations` by `rename`, see
+/// [`Combinations::rewrite_references()`]. Combinations without
+/// renamed references are kept untouched.
+fn rewrite_combinations<F: Fn(&str) -> Option<String>>(
+    combinations: &mut IndexMap<String, Combination>,
+    rename: F,
+) {
+    for combination in combinations.values_mut() {
+        let renamed = combination.sequence.split_whitespace().any(|command| {
+            rename(command.trim_end_matches(|c: char| !c.is_alphabetic())).is_some()
+        });
+        if renamed {
+            combination.sequence = Combinations::rewrite_references(&combination.sequence, &rename);
+        }
+    }
 }
 
 /// Combinations of existing [`Sequences`].
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, JsonSchema, Default, Clone, Debug, PartialEq, Eq)]
 pub struct Combinations {
-    combinations: HashMap<String, Combination>,
+    /// JSON Schema of the file used by editors, see `schema` subcommand.
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    #[schemars(skip)]
+    schema: Option<String>,
+    /// Version of configuration format.
+    #[serde(default)]
+    version: Version,
+    /// Configuration files merged to this one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    include: Vec<Include>,
+    /// Combinations of sequences and other combinations.
+    #[serde(default)]
+    #[schemars(schema_with = "schema::names_map::<Combination>")]
+    combinations: IndexMap<String, Combination>,
+    /// Sequences of text.
+    #[serde(default)]
     sequences: Sequences,
+    /// Custom typers selected by name.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    typers: IndexMap<String, CommandTemplate>,
+    /// Delays in milliseconds after typing of given characters.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    #[serde(serialize_with = "serialize_char_keys")]
+    #[schemars(schema_with = "schema::chars_map::<Delay>")]
+    char_delays: IndexMap<char, Delay>,
+    /// Profiles selected by name overriding sequences and combinations.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    profiles: IndexMap<String, Profile>,
+    /// Configuration files where sequences and combinations are defined.
+    #[serde(skip)]
+    sources: HashMap<String, PathBuf>,
+}
+
+/// Serialize map with [`char`] keys as map with string keys,
+/// because not all formats support other keys than strings.
+fn serialize_char_keys<S: Serializer>(
+    map: &IndexMap<char, Delay>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(map.iter().map(|(c, delay)| (c.to_string(), delay)))
 }
 
 impl From<&str> for Combination {
@@ -24,6 +249,8 @@ impl From<&str> for Combination {
         Self {
             sequence: String::from(value),
             delay: None,
+            start_delay: None,
+            hidden: false,
         }
     }
 }
@@ -41,32 +268,186 @@ impl Combinations {
     /// ```
     pub fn new(sequences: Sequences, combinations: &[(&str, &str)]) -> ATResult<Combinations> {
         let mut comb = Combinations {
-            combinations: HashMap::new(),
             sequences,
+            ..Default::default()
         };
         for (key, value) in combinations.iter() {
-            comb.insert(key, Combination::from(*value))?;
+            comb.insert(key, value)?;
         }
         Ok(comb)
     }
 
+    /// Load [`Combinations`] from configuration file. Format of the file
+    /// is selected by its extension, see [`Format::from_path()`]. Files
+    /// from `include` list are loaded too. If a key is defined in more
+    /// files, error with names of both files is returned.
+    pub fn from_path(path: &Path) -> ATResult<Combinations> {
+        Self::load(path, &mut Vec::new())
+    }
+
+    /// Load single configuration file without resolving its includes,
+    /// so it can be edited and saved back by [`Combinations::to_format()`].
+    pub fn read(path: &Path) -> ATResult<Combinations> {
+        Self::read_migrated(path).map(|(comb, _)| comb)
+    }
+
+    /// Works same as [`Combinations::read()`], but returns also descriptions
+    /// of changes made by migration of configuration from older version.
+    pub fn read_migrated(path: &Path) -> ATResult<(Combinations, Vec<String>)> {
+        let invalid = |message: String| {
+            ErrAutoType::new_with_message(
+                ErrType::InvalidConfig(path.display().to_string()),
+                message,
+            )
+        };
+        let format = Format::from_path(path)?;
+        let content = fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let mut value: serde_json::Value = format.deserialize(&content, path)?;
+        let migration = migration::migrate(&mut value)?;
+        let mut comb: Combinations = match migration.is_reshaped() {
+            true => serde_json::from_value(value).map_err(|e| invalid(e.to_string()))?,
+            false => format.deserialize(&content, path)?,
+        };
+        comb.version = Version::default();
+        comb.sources = comb
+            .list_all_commands()
+            .into_iter()
+            .map(|key| (key.clone(), path.to_path_buf()))
+            .collect();
+        for profile in comb.profiles.values_mut() {
+            profile.sources = profile
+                .keys()
+                .map(|key| (key.clone(), path.to_path_buf()))
+                .collect();
+        }
+        Ok((comb, migration.describe()))
+    }
+
+    /// Load configuration file on `path` with its includes, where `stack`
+    /// are files currently being loaded.
+    fn load(path: &Path, stack: &mut Vec<PathBuf>) -> ATResult<Combinations> {
+        let invalid = |message: String| {
+            ErrAutoType::new_with_message(
+                ErrType::InvalidConfig(path.display().to_string()),
+                message,
+            )
+        };
+        let mut own = Self::read(path)?;
+        let includes = std::mem::take(&mut own.include);
+        if includes.is_empty() {
+            return Ok(own);
+        }
+
+        let canonical = path.canonicalize().map_err(|e| invalid(e.to_string()))?;
+        if stack.contains(&canonical) {
+            return ErrType::IncludeCycle(path.display().to_string()).into();
+        }
+        stack.push(canonical);
+        let mut comb = Combinations::default();
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for include in includes.iter() {
+            for file in include.resolve(dir)? {
+                let mut included = Self::load(&file, stack)?;
+                if let Some(namespace) = include.namespace() {
+                    included.add_namespace(namespace)?;
+                }
+                for key in included.list_all_commands() {
+                    let Some(source) = comb.sources.get(key).or(own.sources.get(key)) else {
+                        continue;
+                    };
+                    let err = match comb.sequences.get(key).or(own.sequences.get(key)) {
+                        Some(_) => ErrType::KeyIsInSequences(key.clone()),
+                        None => ErrType::KeyIsInCombinations(key.clone()),
+                    };
+                    let file = &included.sources[key];
+                    let mut location = Location::entry(key);
+                    if let Ok(content) = fs::read_to_string(file) {
+                        location.find_in(file, &content, None);
+                    }
+                    return Err(ErrAutoType::new_with_message(
+                        err,
+                        format!("({} and {})", source.display(), file.display()),
+                    )
+                    .with_location(location));
+                }
+                comb.merge(included);
+            }
+        }
+        stack.pop();
+        comb.merge(own);
+        Ok(comb)
+    }
+
+    /// Prepend `namespace` to keys of all sequences and combinations,
+    /// including those in profiles, and to references to them.
+    fn add_namespace(&mut self, namespace: &str) -> ATResult<()> {
+        Command::valid_name(namespace)?;
+        let keys = self
+            .list_all_commands()
+            .into_iter()
+            .chain(self.profiles.values().flat_map(Profile::keys))
+            .cloned()
+            .collect::<HashSet<_>>();
+        for key in keys.iter() {
+            self.sequences.rename(key, &format!("{namespace}{key}"));
+            for profile in self.profiles.values_mut() {
+                profile.rename(key, &format!("{namespace}{key}"));
+            }
+        }
+        self.sources = std::mem::take(&mut self.sources)
+            .into_iter()
+            .map(|(key, source)| (format!("{namespace}{key}"), source))
+            .collect();
+        self.combinations = std::mem::take(&mut self.combinations)
+            .into_iter()
+            .map(|(key, mut combination)| {
+                combination.sequence = Self::rewrite_references(&combination.sequence, |name| {
+                    keys.contains(name).then(|| format!("{namespace}{name}"))
+                });
+                (format!("{namespace}{key}"), combination)
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Replace names in `sequence` of combination by `rename`. Names
+    /// for which `rename` returns [`None`] are kept.
+    fn rewrite_references<F: Fn(&str) -> Option<String>>(sequence: &str, rename: F) -> String {
+        sequence
+            .split_whitespace()
+            .map(|command| {
+                let (name, times) = command.split_at(
+                    command
+                        .find(|c: char| c.is_ascii_digit())
+                        .unwrap_or(command.len()),
+                );
+                match rename(name) {
+                    Some(name) => name + times,
+                    None => String::from(command),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Returns JSON Schema of configuration file.
+    pub fn schema() -> RootSchema {
+        schema_for!(Combinations)
+    }
+
+    /// Serialize [`Combinations`] to configuration in `format`.
+    pub fn to_format(&self, format: Format) -> ATResult<String> {
+        format.serialize(self)
+    }
+
     /// Works similarly as [`Combinations::get_sequence()`], only takes reference
     /// to [`Command`] instead of `key`.
     pub fn get_sequence_cmd(&self, command: &Command, args: &Vec<String>) -> ATResult<String> {
-        match self.combinations.get(command.get_name()) {
-            Some(sequence) => {
-                let commands = Self::decompose(&sequence.sequence)?;
-                (0..command.get_times())
-                    .map(|_| {
-                        commands
-                            .iter()
-                            .map(|cmd| self.get_sequence_cmd(cmd, args))
-                            .collect::<ATResult<String>>()
-                    })
-                    .collect()
-            }
-            None => self.sequences.get_sequence_cmd(command, args),
-        }
+        Ok(self
+            .get_segments_cmd(command, args, None, false)?
+            .iter()
+            .map(Segment::get_text)
+            .collect())
     }
 
     /// Generate sequence from given `key`. Returns string with generated
@@ -87,8 +468,230 @@ impl Combinations {
             .collect()
     }
 
-    pub fn get_delay(&self, key: &str) -> Option<usize> {
-        self.combinations.get(key)?.delay
+    /// Generate sequence from given `key` split to [`Segment`]s typed with
+    /// different delays. Delay of sequence is used for its text, sequences
+    /// without delay inherit delay of the closest combination containing them.
+    /// If no delay is found, delay of segment is [`None`]. Unlike
+    /// [`Combinations::get_sequence()`], secret sequences are fetched
+    /// from their provider, so segments contain the typed text.
+    pub fn get_segments(&self, key: &str, args: &Vec<String>) -> ATResult<Vec<Segment>> {
+        let segments = Self::decompose(key)?
+            .iter()
+            .map(|command| self.get_segments_cmd(command, args, None, true))
+            .collect::<ATResult<Vec<_>>>()?;
+        Ok(Segment::merge(segments.concat()))
+    }
+
+    /// Generate segments for `command`, where `inherited` is delay
+    /// of combination containing it. Secret sequences are fetched
+    /// if `reveal` is set, otherwise they are masked.
+    fn get_segments_cmd(
+        &self,
+        command: &Command,
+        args: &Vec<String>,
+        inherited: Option<&Delay>,
+        reveal: bool,
+    ) -> ATResult<Vec<Segment>> {
+        match self.combinations.get(command.get_name()) {
+            Some(combination) => {
+                let delay = combination.delay.as_ref().or(inherited);
+                let commands = Self::decompose(&combination.sequence)?;
+                let mut segments = Vec::new();
+                for _ in 0..command.get_times() {
+                    for cmd in commands.iter() {
+                        segments.append(&mut self.get_segments_cmd(cmd, args, delay, reveal)?);
+                    }
+                }
+                Ok(Segment::merge(segments))
+            }
+            None => Ok(vec![Segment::new(
+                &match reveal {
+                    true => self.sequences.reveal_sequence_cmd(command, args)?,
+                    false => self.sequences.get_sequence_cmd(command, args)?,
+                },
+                self.sequences
+                    .get_delay(command.get_name())
+                    .or(inherited)
+                    .cloned(),
+            )]),
+        }
+    }
+
+    /// Returns delay between key strokes configured for sequence
+    /// or combination with name of `command`.
+    pub fn get_delay(&self, command: &Command) -> Option<&Delay> {
+        match self.combinations.get(command.get_name()) {
+            Some(combination) => combination.delay.as_ref(),
+            None => self.sequences.get_delay(command.get_name()),
+        }
+    }
+
+    /// Returns time in milliseconds to wait before typing of sequence
+    /// or combination with name of `command` starts.
+    pub fn get_start_delay(&self, command: &Command) -> Option<usize> {
+        match self.combinations.get(command.get_name()) {
+            Some(combination) => combination.start_delay,
+            None => self.sequences.get_start_delay(command.get_name()),
+        }
+    }
+
+    /// Split `segments` to characters with delay configured in `char_delays`
+    /// and parts between them. Delay of a character is applied after
+    /// the character is typed.
+    pub fn apply_char_delays(&self, segments: Vec<Segment>) -> Vec<Segment> {
+        if self.char_delays.is_empty() {
+            return segments;
+        }
+        Segment::merge(
+            segments
+                .iter()
+                .flat_map(|segment| segment.split(&self.char_delays))
+                .collect(),
+        )
+    }
+
+    /// Returns [`Kind`] of entry `key` or [`None`] if it does not exist.
+    pub fn get_kind(&self, key: &str) -> Option<Kind> {
+        match (self.sequences.get(key), self.combinations.contains_key(key)) {
+            (_, true) => Some(Kind::Combination),
+            (Some(_), false) => Some(Kind::Sequence),
+            (None, false) => None,
+        }
+    }
+
+    /// Returns `true` if entry `key` is hidden in listings and cheat sheets.
+    ///
+    /// ```
+    /// # use shortcut_autotyper::*;
+    /// let comb: Combinations = serde_json::from_str(r#"{
+    ///     "sequences": {"A": {"text": "a", "hidden": true}, "B": "b"},
+    ///     "combinations": {"X": {"sequence": "A B", "hidden": true}}
+    /// }"#).unwrap();
+    /// assert!(comb.is_hidden("A"));
+    /// assert!(!comb.is_hidden("B"));
+    /// assert!(comb.is_hidden("X"));
+    /// ```
+    pub fn is_hidden(&self, key: &str) -> bool {
+        match self.combinations.get(key) {
+            Some(combination) => combination.hidden,
+            None => self.sequences.is_hidden(key),
+        }
+    }
+
+    /// Returns symbolic expansion of entry `key`. It is text of sequence
+    /// in [`Content`] form with placeholders like `<1>`, or definition
+    /// of combination like `A2 B3..5`.
+    ///
+    /// ```
+    /// # use shortcut_autotyper::*;
+    /// let seq = Sequences::new(&[("A", "Hello <1>")]).unwrap();
+    /// let comb = Combinations::new(seq, &[("X", "A  A2..4")]).unwrap();
+    /// assert_eq!(comb.get_expansion("A").unwrap(), "Hello <1>");
+    /// assert_eq!(comb.get_expansion("X").unwrap(), "A A2..4");
+    /// ```
+    pub fn get_expansion(&self, key: &str) -> Option<String> {
+        match self.combinations.get(key) {
+            Some(combination) => Some(Self::rewrite_references(&combination.sequence, |_| None)),
+            None => Some(Content::from(self.sequences.get(key)?).to_string()),
+        }
+    }
+
+    /// Returns sorted indexes of arguments like `<1>` required by entry
+    /// `key` and all entries it references.
+    ///
+    /// ```
+    /// # use shortcut_autotyper::*;
+    /// let seq = Sequences::new(&[("A", "Hello <1>"), ("B", "<2> and <1>")]).unwrap();
+    /// let comb = Combinations::new(seq, &[("X", "A B2")]).unwrap();
+    /// assert_eq!(comb.get_arguments("A"), vec![1]);
+    /// assert_eq!(comb.get_arguments("X"), vec![1, 2]);
+    /// ```
+    pub fn get_arguments(&self, key: &str) -> Vec<usize> {
+        let mut arguments = self
+            .referenced_sequences(key)
+            .iter()
+            .filter_map(|name| self.sequences.get(name))
+            .flat_map(|text| Content::from(text).get_variables())
+            .collect::<Vec<_>>();
+        arguments.sort();
+        arguments.dedup();
+        arguments
+    }
+
+    /// Returns true if `key` is secret sequence or combination referencing
+    /// one. It may also be sequence of commands like `A2 B`.
+    ///
+    /// ```
+    /// # use shortcut_autotyper::*;
+    /// let comb: Combinations = serde_json::from_str(r#"{
+    ///     "sequences": {"A": "Hello", "T": {"secret": {"env": "TOKEN"}}},
+    ///     "combinations": {"X": {"sequence": "A T"}, "Y": {"sequence": "A2"}}
+    /// }"#).unwrap();
+    /// assert!(comb.uses_secret("T"));
+    /// assert!(comb.uses_secret("X"));
+    /// assert!(!comb.uses_secret("Y"));
+    /// assert!(comb.uses_secret("Y2 T"));
+    /// ```
+    pub fn uses_secret(&self, key: &str) -> bool {
+        self.referenced_sequences(key)
+            .iter()
+            .any(|name| self.sequences.is_secret(name))
+    }
+
+    /// Returns names of sequences typed by entry `key`, directly
+    /// or through other combinations. `key` may also be sequence
+    /// of commands like `A2 B`.
+    fn referenced_sequences(&self, key: &str) -> Vec<String> {
+        let mut sequences = Vec::new();
+        let mut visited = HashSet::new();
+        let mut stack = match Self::decompose(key) {
+            Ok(commands) => commands
+                .iter()
+                .map(|command| String::from(command.get_name()))
+                .collect(),
+            Err(_) => vec![String::from(key)],
+        };
+        while let Some(current) = stack.pop() {
+            if !visited.insert(current.clone()) {
+                continue;
+            }
+            match self.combinations.get(&current) {
+                Some(combination) => stack.extend(
+                    Self::decompose(&combination.sequence)
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|command| String::from(command.get_name())),
+                ),
+                None => sequences.push(current),
+            }
+        }
+        sequences
+    }
+
+    /// Returns typer defined in configuration under `name`.
+    pub fn get_typer(&self, name: &str) -> Option<&CommandTemplate> {
+        self.typers.get(name)
+    }
+
+    /// Merge `other` over `self`. Sequences, combinations, typers and
+    /// character delays from `other` replace those with the same key,
+    /// even if the key was sequence in one and combination in the other.
+    /// Profiles with the same name are merged in the same way.
+    pub fn merge(&mut self, other: Combinations) {
+        other.sequences.get_keys().for_each(|key| {
+            self.combinations.shift_remove(key);
+        });
+        other.combinations.keys().for_each(|key| {
+            self.sequences.remove(key);
+        });
+        self.sequences.merge(other.sequences);
+        self.combinations.extend(other.combinations);
+        for (name, profile) in other.profiles {
+            self.profiles.entry(name).or_default().merge(profile);
+        }
+        self.typers.extend(other.typers);
+        self.char_delays.extend(other.char_delays);
+        self.sources.extend(other.sources);
     }
 
     /// Decompose string to list of [`Command`]s.
@@ -103,49 +706,364 @@ impl Combinations {
     /// returns `Ok(())`.
     pub fn get_errors(&self) -> ATVecResult<()> {
         let mut errors = Vec::new();
-        if let Err(e) = &mut Command::are_valid_names(self.combinations.keys()) {
-            errors.append(e)
-        }
-        if let Err(e) = &mut self.sequences.get_errors() {
-            errors.append(e)
-        }
-        self.combinations.values().for_each(|combination| {
-            match Combinations::decompose(&combination.sequence) {
-                Ok(commands) => commands.iter().for_each(|command| match command.valid() {
-                    Ok(_) => match self.sequences.get(command.get_name()) {
-                        Some(_) => {}
-                        None => errors.push(ErrAutoType::new(ErrType::UnknownSequence(
-                            String::from(command.get_name()),
-                        ))),
-                    },
-                    Err(e) => errors.push(e),
-                }),
-                Err(e) => errors.push(e),
+        let mut keys = self.list_all_commands();
+        keys.dedup();
+        for key in keys {
+            if let Err(e) = Command::valid_name(key) {
+                errors.push(e.with_location(Location::entry(key)));
             }
-        });
+        }
+        let mut keys = self.combinations.keys().collect::<Vec<_>>();
+        keys.sort();
+        for key in keys {
+            let error = |err_type: ErrType, span: Option<Range<usize>>| {
+                let location = Location::entry(key);
+                ErrAutoType::new(err_type).with_location(match span {
+                    Some(span) => location.with_span(span),
+                    None => location,
+                })
+            };
+            if self.sequences.get(key).is_some() {
+                errors.push(error(ErrType::KeyIsInSequences(key.clone()), None));
+            }
+            for (span, token) in Self::tokens(&self.combinations[key].sequence) {
+                match Command::from_str(token) {
+                    Ok(command) if self.contains(command.get_name()) => {}
+                    Ok(command) => errors.push(error(
+                        ErrType::UnknownSequence(String::from(command.get_name())),
+                        Some(span.start..span.start + command.get_name().len()),
+                    )),
+                    Err(e) => {
+                        let narrowed = match e.get_type() {
+                            ErrType::InvalidKeyFormat(s) | ErrType::WrongSequenceArg(s) => token
+                                .find(s.as_str())
+                                .map(|i| span.start + i..span.start + i + s.len()),
+                            _ => None,
+                        };
+                        let location = Location::entry(key).with_span(narrowed.unwrap_or(span));
+                        errors.push(e.with_location(location));
+                    }
+                }
+            }
+            if self.is_recursive(key) {
+                errors.push(error(ErrType::CombinationCycle(key.clone()), None));
+            }
+        }
         if errors.is_empty() {
-            Ok(())
-        } else {
-            Err(errors)
+            return Ok(());
+        }
+        self.find_locations(&mut errors);
+        Err(errors)
+    }
+
+    /// Find locations of `errors` in configuration files, where entries
+    /// with errors are defined.
+    fn find_locations(&self, errors: &mut [ErrAutoType]) {
+        let mut contents = HashMap::new();
+        for location in errors.iter_mut().filter_map(ErrAutoType::get_location_mut) {
+            let Some(entry) = location.get_entry().map(String::from) else {
+                continue;
+            };
+            let Some(file) = self.sources.get(&entry) else {
+                continue;
+            };
+            let content = contents
+                .entry(file)
+                .or_insert_with(|| fs::read_to_string(file).ok());
+            if let Some(content) = content {
+                let text = self.combinations.get(&entry).map(|c| c.sequence.as_str());
+                location.find_in(file, content, text);
+            }
         }
     }
 
+    /// Split combination `sequence` to commands with their byte spans.
+    fn tokens(sequence: &str) -> impl Iterator<Item = (Range<usize>, &str)> {
+        sequence.split_whitespace().map(move |token| {
+            let start = token.as_ptr() as usize - sequence.as_ptr() as usize;
+            (start..start + token.len(), token)
+        })
+    }
+
+    /// Returns [`ErrType::InvalidConfigContent`] with all errors
+    /// from [`Combinations::get_errors()`] if there are any.
+    pub fn validate(&self) -> ATResult<()> {
+        self.get_errors().map_err(|errors| {
+            ErrType::InvalidConfigContent(errors.iter().map(ErrAutoType::describe).collect()).into()
+        })
+    }
+
     /// Check if [`Combinations`] are valid.
     pub fn is_valid(&self) -> bool {
-        !self.combinations.iter().any(|(key, value)| {
-            Command::valid_name(key).is_err()
-                || match Self::decompose(&value.sequence) {
-                    Ok(combinations) => combinations
-                        .iter()
-                        .any(|command| self.sequences.get(command.get_name()).is_none()),
-                    Err(_) => true,
+        self.get_errors().is_ok()
+    }
+
+    /// Returns error if `key` is already used by a sequence
+    /// or a combination.
+    ///
+    /// ```
+    /// # use shortcut_autotyper::*;
+    /// let comb = Combinations::new(Sequences::new(&[("A", "a")]).unwrap(), &[("X", "A2")]).unwrap();
+    /// assert!(comb.check_unused("A").is_err());
+    /// assert!(comb.check_unused("X").is_err());
+    /// assert!(comb.check_unused("B").is_ok());
+    /// ```
+    pub fn check_unused(&self, key: &str) -> ATResult<()> {
+        match self.get_kind(key) {
+            Some(Kind::Sequence) => ErrType::KeyIsInSequences(String::from(key)).into(),
+            Some(Kind::Combination) => ErrType::KeyIsInCombinations(String::from(key)).into(),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns `true` if `key` is in sequences or combinations.
+    fn contains(&self, key: &str) -> bool {
+        self.sequences.get(key).is_some() || self.combinations.contains_key(key)
+    }
+
+    /// Returns `true` if combination `key` references itself directly
+    /// or through other combinations.
+    fn is_recursive(&self, key: &str) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![key];
+        while let Some(current) = stack.pop() {
+            let Some(combination) = self.combinations.get(current) else {
+                continue;
+            };
+            for command in Self::decompose(&combination.sequence).unwrap_or_default() {
+                let Some((name, _)) = self.combinations.get_key_value(command.get_name()) else {
+                    continue;
+                };
+                if name == key {
+                    return true;
                 }
-        })
+                if visited.insert(name) {
+                    stack.push(name);
+                }
+            }
+        }
+        false
+    }
+
+    /// Insert new combination with `sequence` of sequences and combinations
+    /// like `"A2 B"`. See [`Combinations::insert_combination()`].
+    ///
+    /// ```
+    /// # use shortcut_autotyper::*;
+    /// let mut comb = Combinations::new(Sequences::new(&[("A", "a")]).unwrap(), &[]).unwrap();
+    /// comb.insert("X", "A3").unwrap();
+    /// assert_eq!(comb.get_sequence("X", &Vec::new()).unwrap(), "aaa");
+    /// assert!(comb.insert("Y", "B").is_err());
+    /// ```
+    pub fn insert(&mut self, key: &str, sequence: &str) -> ATResult<()> {
+        self.insert_combination(key, Combination::from(sequence), None)
+    }
+
+    /// Insert new combination same as [`Combinations::insert()`], but
+    /// `sequence` may also reference entries of `base`. It is used when
+    /// a single file is edited and `base` is the merged configuration
+    /// with its includes and other layers.
+    ///
+    /// ```
+    /// # use shortcut_autotyper::*;
+    /// let base = Combinations::new(Sequences::new(&[("A", "a")]).unwrap(), &[]).unwrap();
+    /// let mut comb = Combinations::default();
+    /// comb.insert_with_base("X", "A3", &base).unwrap();
+    /// assert_eq!(comb.get_expansion("X").unwrap(), "A3");
+    /// assert!(comb.insert_with_base("Y", "B", &base).is_err());
+    /// ```
+    pub fn insert_with_base(
+        &mut self,
+        key: &str,
+        sequence: &str,
+        base: &Combinations,
+    ) -> ATResult<()> {
+        self.insert_combination(key, Combination::from(sequence), Some(base))
+    }
+
+    /// Insert new sequence with `text` by [`Sequences::insert()`]. Returns
+    /// error if `key` is already used by a combination.
+    pub fn insert_sequence(&mut self, key: &str, text: &str) -> ATResult<()> {
+        if self.combinations.contains_key(key) {
+            return ErrType::KeyIsInCombinations(String::from(key)).into();
+        }
+        self.sequences.insert(key, text)
+    }
+
+    /// Remove sequence or combination `key` from base configuration
+    /// and all profiles. Returns names of profiles whose overrides
+    /// of `key` were removed. Returns error if it does not exist or if
+    /// combinations reference it.
+    pub fn remove(&mut self, key: &str) -> ATResult<Vec<String>> {
+        if !self.contains_anywhere(key) {
+            return ErrType::SequenceNotExist(String::from(key)).into();
+        }
+        let referencing = self.referencing(key);
+        if !referencing.is_empty() {
+            return ErrType::KeyIsReferenced(String::from(key), referencing).into();
+        }
+        let mut overridden = Vec::new();
+        for (name, profile) in self.profiles.iter_mut() {
+            let combination = profile.combinations.shift_remove(key).is_some();
+            profile.sources.remove(key);
+            if profile.sequences.remove(key) || combination {
+                overridden.push(name.clone());
+            }
+        }
+        self.combinations.shift_remove(key);
+        self.sequences.remove(key);
+        self.sources.remove(key);
+        Ok(overridden)
+    }
+
+    /// Rename sequence or combination `key` to `new_key` and rewrite
+    /// all combinations referencing it.
+    ///
+    /// ```
+    /// # use shortcut_autotyper::*;
+    /// let seq = Sequences::new(&[("A", "a")]).unwrap();
+    /// let mut comb = Combinations::new(seq, &[("X", "A2")]).unwrap();
+    /// comb.rename("A", "B").unwrap();
+    /// assert_eq!(comb.get_sequence("X", &Vec::new()).unwrap(), "aa");
+    /// assert!(comb.get_sequence("A", &Vec::new()).is_err());
+    /// ```
+    pub fn rename(&mut self, key: &str, new_key: &str) -> ATResult<()> {
+        Command::valid_name(new_key)?;
+        if !self.contains_anywhere(key) {
+            return ErrType::SequenceNotExist(String::from(key)).into();
+        }
+        let profiles = self.profiles.values();
+        if self.sequences.get(new_key).is_some()
+            || profiles.clone().any(|p| p.sequences.get(new_key).is_some())
+        {
+            return ErrType::KeyIsInSequences(String::from(new_key)).into();
+        }
+        if self.combinations.contains_key(new_key)
+            || profiles
+                .clone()
+                .any(|p| p.combinations.contains_key(new_key))
+        {
+            return ErrType::KeyIsInCombinations(String::from(new_key)).into();
+        }
+        let mut base = Profile {
+            combinations: std::mem::take(&mut self.combinations),
+            sequences: std::mem::take(&mut self.sequences),
+            sources: std::mem::take(&mut self.sources),
+        };
+        base.rename(key, new_key);
+        self.combinations = base.combinations;
+        self.sequences = base.sequences;
+        self.sources = base.sources;
+        for profile in self.profiles.values_mut() {
+            profile.rename(key, new_key);
+        }
+        Ok(())
+    }
+
+    /// Returns sorted names of combinations with reference to `key`.
+    /// Combinations of profiles are followed by name of the profile.
+    fn referencing(&self, key: &str) -> Vec<String> {
+        let references = |combination: &Combination| {
+            Self::decompose(&combination.sequence)
+                .unwrap_or_default()
+                .iter()
+                .any(|command| command.get_name() == key)
+        };
+        let mut referencing = self
+            .combinations
+            .iter()
+            .filter(|(_, combination)| references(combination))
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        referencing.sort();
+        for (profile, entries) in self.profiles.iter() {
+            let mut names = entries
+                .combinations
+                .iter()
+                .filter(|(_, combination)| references(combination))
+                .map(|(name, _)| format!("{name} (profile {profile})"))
+                .collect::<Vec<_>>();
+            names.sort();
+            referencing.extend(names);
+        }
+        referencing
+    }
+
+    /// Returns `true` if `key` is in base configuration or any profile.
+    fn contains_anywhere(&self, key: &str) -> bool {
+        self.contains(key)
+            || self
+                .profiles
+                .values()
+                .any(|profile| profile.keys().any(|k| k == key))
+    }
+
+    /// Returns names of all profiles in order of configuration.
+    pub fn list_profiles(&self) -> Vec<&String> {
+        self.profiles.keys().collect()
+    }
+
+    /// Apply profile `name` over the base configuration. Sequences
+    /// and combinations of the profile replace those with the same key.
+    /// Returns [`ErrType::UnknownProfile`] if the profile is not defined.
+    ///
+    /// ```
+    /// # use shortcut_autotyper::*;
+    /// let comb: Combinations = serde_json::from_str(r#"{
+    ///     "sequences": {"Sig": "Bye", "Name": "John"},
+    ///     "combinations": {"Mail": {"sequence": "Sig Name"}},
+    ///     "profiles": {"work": {"sequences": {"Sig": "Regards, "}}}
+    /// }"#).unwrap();
+    /// let work = comb.clone().with_profile("work").unwrap();
+    /// assert_eq!(work.get_sequence("Mail", &Vec::new()).unwrap(), "Regards, John");
+    /// assert_eq!(comb.get_sequence("Mail", &Vec::new()).unwrap(), "ByeJohn");
+    /// assert!(comb.with_profile("home").is_err());
+    /// ```
+    pub fn with_profile(mut self, name: &str) -> ATResult<Combinations> {
+        let Some(profile) = self.profiles.shift_remove(name) else {
+            return ErrType::UnknownProfile(String::from(name)).into();
+        };
+        self.profiles.clear();
+        self.merge(Combinations {
+            combinations: profile.combinations,
+            sequences: profile.sequences,
+            sources: profile.sources,
+            ..Default::default()
+        });
+        Ok(self)
+    }
+
+    /// Returns errors of configuration with profile `name` applied, which
+    /// are not in the base configuration. See [`Combinations::get_errors()`].
+    pub fn get_profile_errors(&self, name: &str) -> ATVecResult<()> {
+        let base = match self.get_errors() {
+            Ok(()) => HashSet::new(),
+            Err(errors) => errors.iter().map(ErrAutoType::describe).collect(),
+        };
+        let profile = self.clone().with_profile(name).map_err(|e| vec![e])?;
+        let errors = match profile.get_errors() {
+            Ok(()) => return Ok(()),
+            Err(errors) => errors,
+        };
+        let errors = errors
+            .into_iter()
+            .filter(|e| !base.contains(&e.describe()))
+            .collect::<Vec<_>>();
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
     }
 
     /// Insert new combination to existing combinations if `key` is valid
-    /// and in `value` are only existing [`Sequences`] or [`Combinations`].
-    fn insert(&mut self, key: &str, combination: Combination) -> ATResult<()> {
+    /// and in `value` are only existing [`Sequences`] or [`Combinations`],
+    /// either of these or of `base`.
+    fn insert_combination(
+        &mut self,
+        key: &str,
+        combination: Combination,
+        base: Option<&Combinations>,
+    ) -> ATResult<()> {
         Command::valid_name(key)?;
         if self.sequences.get(key).is_some() {
             return ErrType::KeyIsInSequences(String::from(key)).into();
@@ -154,10 +1072,8 @@ impl Combinations {
             return ErrType::KeyIsInCombinations(String::from(key)).into();
         };
         let commands = Self::decompose(&combination.sequence)?;
-        if let Some(cmd) = commands
-            .iter()
-            .find(|cmd| self.sequences.get(cmd.get_name()).is_none())
-        {
+        let exists = |name: &str| self.contains(name) || base.is_some_and(|b| b.contains(name));
+        if let Some(cmd) = commands.iter().find(|cmd| !exists(cmd.get_name())) {
             return ErrType::SequenceNotExist(String::from(cmd.get_name())).into();
         };
 
@@ -187,11 +1103,43 @@ impl Combinations {
         commands.sort();
         commands
     }
+
+    /// Returns all sequences and combinations with their [`Kind`]
+    /// in given `order`.
+    ///
+    /// ```
+    /// # use shortcut_autotyper::*;
+    /// let seq = Sequences::new(&[("B", "b"), ("A", "a")]).unwrap();
+    /// let comb = Combinations::new(seq, &[("C", "A B")]).unwrap();
+    /// let names = |order| {
+    ///     comb.list_commands(order)
+    ///         .into_iter()
+    ///         .map(|(_, name)| name.as_str())
+    ///         .collect::<Vec<_>>()
+    /// };
+    /// assert_eq!(names(ListOrder::Config), vec!["B", "A", "C"]);
+    /// assert_eq!(names(ListOrder::Name), vec!["A", "B", "C"]);
+    /// ```
+    pub fn list_commands(&self, order: ListOrder) -> Vec<(Kind, &String)> {
+        let mut commands = self
+            .sequences
+            .get_keys()
+            .map(|key| (Kind::Sequence, key))
+            .chain(self.combinations.keys().map(|key| (Kind::Combination, key)))
+            .collect::<Vec<_>>();
+        match order {
+            ListOrder::Config => {}
+            ListOrder::Name => commands.sort_by_key(|(_, key)| *key),
+            ListOrder::Kind => commands.sort(),
+        }
+        commands
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile::TempDir;
 
     fn example_combination() -> Combinations {
         Combinations::new(
@@ -252,7 +1200,7 @@ mod tests {
         let get_sequence =
             || Sequences::new(&[("A", "A1"), ("B", "B1"), ("AB", "AB1"), ("BA", "BA1")]).unwrap();
         let get_combinations = |combs: &[(&str, &str)]| {
-            let mut combinations = HashMap::new();
+            let mut combinations = IndexMap::new();
             combs.iter().for_each(|(key, value)| {
                 combinations.insert(String::from(*key), Combination::from(*value));
             });
@@ -266,6 +1214,7 @@ mod tests {
         let errors = Combinations {
             sequences: get_sequence(),
             combinations: get_combinations(&[("X", "A3 B~3..5"), ("Y", "A C3")]),
+            ..Default::default()
         }
         .get_errors()
         .unwrap_err();
@@ -284,12 +1233,59 @@ mod tests {
         }
     }
 
+    #[test]
+    fn get_errors_nested() {
+        let comb = serde_json::from_str::<Combinations>(
+            r#"{
+                "sequences": {"A": "a", "Z": "z"},
+                "combinations": {
+                    "X": {"sequence": "A Y2"},
+                    "Y": {"sequence": "A"},
+                    "Z": {"sequence": "A"},
+                    "P": {"sequence": "Q"},
+                    "Q": {"sequence": "A R"},
+                    "R": {"sequence": "P2"},
+                    "S": {"sequence": "S"},
+                    "T": {"sequence": "P"}
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            comb.get_errors().unwrap_err(),
+            vec![
+                ErrAutoType::new(ErrType::CombinationCycle(String::from("P"))),
+                ErrAutoType::new(ErrType::CombinationCycle(String::from("Q"))),
+                ErrAutoType::new(ErrType::CombinationCycle(String::from("R"))),
+                ErrAutoType::new(ErrType::CombinationCycle(String::from("S"))),
+                ErrAutoType::new(ErrType::KeyIsInSequences(String::from("Z"))),
+            ]
+        );
+        assert!(!comb.is_valid());
+        assert_eq!(
+            comb.validate().unwrap_err().to_string(),
+            "Error: Configuration is not valid:\n\
+             Combination \"P\" references itself (in \"P\")\n\
+             Combination \"Q\" references itself (in \"Q\")\n\
+             Combination \"R\" references itself (in \"R\")\n\
+             Combination \"S\" references itself (in \"S\")\n\
+             Key \"Z\" is now in sequences (in \"Z\")"
+        );
+        let valid = Combinations::new(
+            Sequences::new(&[("A", "a")]).unwrap(),
+            &[("X", "A2"), ("Y", "X A")],
+        )
+        .unwrap();
+        assert_eq!(valid.validate(), Ok(()));
+        assert_eq!(valid.get_sequence("Y", &Vec::new()).unwrap(), "aaa");
+    }
+
     #[test]
     fn is_valid() {
         let get_sequence =
             || Sequences::new(&[("A", "A1"), ("B", "B1"), ("AB", "AB1"), ("BA", "BA1")]).unwrap();
         let get_combinations = |combs: &[(&str, &str)]| {
-            let mut combinations = HashMap::new();
+            let mut combinations = IndexMap::new();
             combs.iter().for_each(|(key, value)| {
                 combinations.insert(String::from(*key), Combination::from(*value));
             });
@@ -302,11 +1298,13 @@ mod tests {
         assert!(!Combinations {
             sequences: get_sequence(),
             combinations: get_combinations(&[("X", "A3 B~3..5")]),
+            ..Default::default()
         }
         .is_valid());
         assert!(!Combinations {
             sequences: get_sequence(),
             combinations: get_combinations(&[("X", "A3 C3..5")]),
+            ..Default::default()
         }
         .is_valid());
     }
@@ -316,35 +1314,121 @@ mod tests {
         let mut comb = Combinations {
             sequences: Sequences::new(&[("A", "A1"), ("B", "B1"), ("AB", "AB1"), ("BA", "BA1")])
                 .unwrap(),
-            combinations: HashMap::new(),
+            ..Default::default()
         };
-        comb.insert("X", Combination::from("A5"))?;
+        comb.insert("X", "A5")?;
         assert!(comb.combinations.contains_key("X"));
-        comb.insert("Y", "B4 AB1..3".into())?;
+        comb.insert("Y", "B4 AB1..3")?;
         assert!(comb.combinations.contains_key("Y"));
 
         assert_eq!(
-            comb.insert("X", Combination::from("")),
+            comb.insert("X", ""),
             ErrType::KeyIsInCombinations(String::from("X")).into()
         );
         assert_eq!(
-            comb.insert("Y", Combination::from("")),
+            comb.insert("Y", ""),
             ErrType::KeyIsInCombinations(String::from("Y")).into()
         );
 
         assert_eq!(
-            comb.insert("A", Combination::from("")),
+            comb.insert("A", ""),
             ErrType::KeyIsInSequences(String::from("A")).into()
         );
 
         assert_eq!(
-            comb.insert("AB", Combination::from("")),
+            comb.insert("AB", ""),
             ErrType::KeyIsInSequences(String::from("AB")).into()
         );
 
         Ok(())
     }
 
+    #[test]
+    fn remove_rename() -> ATResult<()> {
+        let mut comb = Combinations::new(
+            Sequences::new(&[("A", "a"), ("B", "b")])?,
+            &[("X", "A2 B"), ("Y", "X  A3..4"), ("Z", "B")],
+        )?;
+        assert_eq!(
+            comb.remove("A"),
+            ErrType::KeyIsReferenced(
+                String::from("A"),
+                vec![String::from("X"), String::from("Y")]
+            )
+            .into()
+        );
+        assert_eq!(
+            comb.remove("C"),
+            ErrType::SequenceNotExist(String::from("C")).into()
+        );
+        comb.remove("Z")?;
+        assert!(!comb.contains("Z"));
+
+        comb.rename("A", "C")?;
+        comb.rename("X", "W")?;
+        assert_eq!(comb.combinations["W"].sequence, "C2 B");
+        assert_eq!(comb.combinations["Y"].sequence, "W C3..4");
+        assert_eq!(
+            comb.rename("B", "C"),
+            ErrType::KeyIsInSequences(String::from("C")).into()
+        );
+        assert_eq!(
+            comb.rename("B", "Y"),
+            ErrType::KeyIsInCombinations(String::from("Y")).into()
+        );
+        assert!(comb.rename("B", "B~").is_err());
+        assert!(comb.is_valid());
+        Ok(())
+    }
+
+    #[test]
+    fn profiles() -> ATResult<()> {
+        let mut comb = serde_json::from_str::<Combinations>(
+            r#"{
+                "sequences": {"A": "a", "B": "b"},
+                "combinations": {"X": {"sequence": "A B"}},
+                "profiles": {
+                    "work": {"sequences": {"A": "w"}, "combinations": {"Y": {"sequence": "X2"}}},
+                    "broken": {"combinations": {"Z": {"sequence": "C"}}}
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(comb.list_profiles(), vec!["work", "broken"]);
+        assert_eq!(comb.get_profile_errors("work"), Ok(()));
+        assert_eq!(comb.get_profile_errors("broken").unwrap_err().len(), 1);
+        assert!(comb.get_profile_errors("home").is_err());
+
+        let mut other = Combinations::default();
+        other.profiles.insert(
+            String::from("work"),
+            Profile {
+                sequences: Sequences::new(&[("B", "v")])?,
+                ..Default::default()
+            },
+        );
+        comb.merge(other);
+        let work = comb.clone().with_profile("work")?;
+        assert_eq!(work.get_sequence("Y", &Vec::new())?, "wvwv");
+        assert!(work.list_profiles().is_empty());
+
+        assert_eq!(
+            comb.remove("X"),
+            ErrType::KeyIsReferenced(String::from("X"), vec![String::from("Y (profile work)")])
+                .into()
+        );
+        assert_eq!(
+            comb.rename("B", "Y"),
+            ErrType::KeyIsInCombinations(String::from("Y")).into()
+        );
+        comb.rename("X", "W")?;
+        assert_eq!(comb.remove("Z")?, vec![String::from("broken")]);
+        let work = comb.clone().with_profile("work")?;
+        assert_eq!(work.combinations["Y"].sequence, "W2");
+        assert_eq!(comb.get_profile_errors("broken"), Ok(()));
+        Ok(())
+    }
+
     #[test]
     fn de_serialization() {
         let comb = example_combination();
@@ -353,4 +1437,392 @@ mod tests {
         let deserialized = serde_json::from_str::<Combinations>(&serialized).unwrap();
         assert_eq!(comb, deserialized);
     }
+
+    #[test]
+    fn get_start_delay() {
+        let comb = serde_json::from_str::<Combinations>(
+            r#"{
+                "combinations": {
+                    "X": {"sequence": "A2", "start_delay": 300},
+                    "Y": {"sequence": "A2", "delay": 10}
+                },
+                "sequences": {"A": "A1"}
+            }"#,
+        )
+        .unwrap();
+        let start_delay = |key| comb.get_start_delay(&Command::from_str(key).unwrap());
+        assert_eq!(start_delay("X"), Some(300));
+        assert_eq!(start_delay("X3"), Some(300));
+        assert_eq!(start_delay("Y"), None);
+        assert_eq!(start_delay("A"), None);
+        assert_eq!(start_delay("Z"), None);
+    }
+
+    #[test]
+    fn get_delay() {
+        let comb = serde_json::from_str::<Combinations>(
+            r#"{
+                "combinations": {
+                    "X": {"sequence": "A2 B", "delay": 100},
+                    "Y": {"sequence": "A2"}
+                },
+                "sequences": {"A": "A1", "B": {"text": "B1", "delay": 10}}
+            }"#,
+        )
+        .unwrap();
+        let delay = |key| comb.get_delay(&Command::from_str(key).unwrap());
+        assert_eq!(delay("X"), Some(&Delay::Fixed(100)));
+        assert_eq!(delay("X3"), Some(&Delay::Fixed(100)));
+        assert_eq!(delay("X2..4"), Some(&Delay::Fixed(100)));
+        assert_eq!(delay("Y"), None);
+        assert_eq!(delay("A"), None);
+        assert_eq!(delay("B2"), Some(&Delay::Fixed(10)));
+        assert_eq!(delay("Z"), None);
+    }
+
+    #[test]
+    fn secrets() -> ATResult<()> {
+        let name = "SHORTCUT_AUTOTYPER_TEST_TOKEN";
+        crate::secret::set_test_var(name, Some("t<1>ken"));
+        let comb = serde_json::from_str::<Combinations>(&format!(
+            r#"{{
+                "combinations": {{"X": {{"sequence": "A T2"}}}},
+                "sequences": {{"A": "a", "T": {{"secret": {{"env": "{name}"}}, "delay": 10}}}}
+            }}"#
+        ))
+        .unwrap();
+        let args = vec![String::new(), String::from("x")];
+        assert_eq!(comb.get_sequence("X", &args)?, "a****************");
+        assert_eq!(comb.get_expansion("T").unwrap(), "********");
+        assert_eq!(
+            comb.get_segments("X", &args)?,
+            vec![
+                Segment::new("a", None),
+                Segment::new("t<1>kent<1>ken", Some(Delay::Fixed(10)))
+            ]
+        );
+        assert!(!comb.to_format(Format::Json)?.contains("t<1>ken"));
+        crate::secret::set_test_var(name, None);
+        assert_eq!(
+            comb.get_segments("X", &args),
+            ErrType::SecretUnavailable(String::from("T")).into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn get_segments() -> ATResult<()> {
+        let comb = serde_json::from_str::<Combinations>(
+            r#"{
+                "combinations": {
+                    "X": {"sequence": "A2 B", "delay": 100},
+                    "Y": {"sequence": "X A"},
+                    "Z": {"sequence": "X2 Y", "delay": "20..40"}
+                },
+                "sequences": {"A": "a", "B": {"text": "b", "delay": 10}}
+            }"#,
+        )
+        .unwrap();
+        let args = Vec::new();
+        assert_eq!(
+            comb.get_segments("A3", &args)?,
+            vec![Segment::new("aaa", None)]
+        );
+        assert_eq!(
+            comb.get_segments("B", &args)?,
+            vec![Segment::new("b", Some(Delay::Fixed(10)))]
+        );
+        assert_eq!(
+            comb.get_segments("X2", &args)?,
+            vec![
+                Segment::new("aa", Some(Delay::Fixed(100))),
+                Segment::new("b", Some(Delay::Fixed(10))),
+                Segment::new("aa", Some(Delay::Fixed(100))),
+                Segment::new("b", Some(Delay::Fixed(10))),
+            ]
+        );
+        assert_eq!(
+            comb.get_segments("Y", &args)?,
+            vec![
+                Segment::new("aa", Some(Delay::Fixed(100))),
+                Segment::new("b", Some(Delay::Fixed(10))),
+                Segment::new("a", None),
+            ]
+        );
+        assert_eq!(
+            comb.get_segments("Z", &args)?,
+            vec![
+                Segment::new("aa", Some(Delay::Fixed(100))),
+                Segment::new("b", Some(Delay::Fixed(10))),
+                Segment::new("aa", Some(Delay::Fixed(100))),
+                Segment::new("b", Some(Delay::Fixed(10))),
+                Segment::new("aa", Some(Delay::Fixed(100))),
+                Segment::new("b", Some(Delay::Fixed(10))),
+                Segment::new("a", Some(Delay::Range(20..40))),
+            ]
+        );
+        assert_eq!(comb.get_sequence("Z", &args)?, "aabaabaaba");
+        Ok(())
+    }
+
+    #[test]
+    fn apply_char_delays() -> ATResult<()> {
+        let comb = serde_json::from_str::<Combinations>(
+            r#"{
+                "combinations": {"X": {"sequence": "A B", "delay": 20}},
+                "sequences": {"A": "a\n", "B": "\tb"},
+                "char_delays": {"\n": 250, "\t": "100..200"}
+            }"#,
+        )
+        .unwrap();
+        let segments = comb.get_segments("X", &Vec::new())?;
+        assert_eq!(
+            segments,
+            vec![Segment::new("a\n\tb", Some(Delay::Fixed(20)))]
+        );
+        assert_eq!(
+            comb.apply_char_delays(segments),
+            vec![
+                Segment::new("a", Some(Delay::Fixed(20))),
+                Segment::new("\n", Some(Delay::Fixed(250))),
+                Segment::new("\t", Some(Delay::Range(100..200))),
+                Segment::new("b", Some(Delay::Fixed(20))),
+            ]
+        );
+        assert!(serde_json::from_str::<Combinations>(
+            r#"{"combinations": {}, "sequences": {}, "char_delays": {"ab": 10}}"#
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn typers() {
+        let comb = serde_json::from_str::<Combinations>(
+            r#"{
+                "combinations": {},
+                "sequences": {},
+                "typers": {"dotool": {"program": "dotool", "args": ["type", "{text}"]}}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            comb.get_typer("dotool"),
+            Some(&CommandTemplate::new("dotool", &["type", "{text}"], false))
+        );
+        assert_eq!(comb.get_typer("kdotool"), None);
+    }
+
+    #[test]
+    fn merge() {
+        let mut comb = serde_json::from_str::<Combinations>(
+            r#"{
+                "combinations": {"X": {"sequence": "A B"}, "Y": {"sequence": "A"}},
+                "sequences": {"A": "a", "B": "b", "C": "c"},
+                "char_delays": {"\n": 250}
+            }"#,
+        )
+        .unwrap();
+        comb.merge(
+            serde_json::from_str(
+                r#"{
+                    "combinations": {"C": {"sequence": "A2"}},
+                    "sequences": {"A": "user a", "X": "x"},
+                    "typers": {"dotool": {"program": "dotool"}}
+                }"#,
+            )
+            .unwrap(),
+        );
+        assert_eq!(comb.get_sequence("X", &Vec::new()).unwrap(), "x");
+        assert_eq!(comb.get_sequence("Y", &Vec::new()).unwrap(), "user a");
+        assert_eq!(comb.get_sequence("C", &Vec::new()).unwrap(), "user auser a");
+        assert_eq!(comb.get_sequence("B", &Vec::new()).unwrap(), "b");
+        assert_eq!(comb.list_all_commands(), vec!["A", "B", "C", "X", "Y"]);
+        assert!(comb.get_typer("dotool").is_some());
+        assert_eq!(comb.char_delays.get(&'\n'), Some(&Delay::Fixed(250)));
+    }
+
+    #[test]
+    fn list_commands() {
+        let mut comb = serde_json::from_str::<Combinations>(
+            r#"{
+                "sequences": {"C": "c", "A": "a", "B": "b"},
+                "combinations": {"Z": {"sequence": "C"}, "X": {"sequence": "A"}}
+            }"#,
+        )
+        .unwrap();
+        let names = |comb: &Combinations, order| {
+            comb.list_commands(order)
+                .into_iter()
+                .map(|(_, name)| name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&comb, ListOrder::Config), ["C", "A", "B", "Z", "X"]);
+        assert_eq!(names(&comb, ListOrder::Name), ["A", "B", "C", "X", "Z"]);
+        assert_eq!(
+            comb.list_commands(ListOrder::Kind)
+                .into_iter()
+                .map(|(kind, _)| kind)
+                .collect::<Vec<_>>(),
+            [[Kind::Sequence; 3].as_slice(), &[Kind::Combination; 2]].concat()
+        );
+
+        comb.rename("A", "D").unwrap();
+        comb.remove("Z").unwrap();
+        comb.remove("C").unwrap();
+        for format in [Format::Json, Format::Toml, Format::Yaml] {
+            let content = comb.to_format(format).unwrap();
+            let loaded: Combinations = format.deserialize(&content, Path::new("")).unwrap();
+            assert_eq!(
+                names(&loaded, ListOrder::Config),
+                ["D", "B", "X"],
+                "{content}"
+            );
+        }
+    }
+
+    #[test]
+    fn formats() {
+        let comb = serde_json::from_str::<Combinations>(
+            r#"{
+                "combinations": {"X": {"sequence": "A B2", "delay": "10..20", "start_delay": null}},
+                "sequences": {"A": "multi\nline", "B": {"text": "b", "delay": 5, "start_delay": 100}},
+                "typers": {"dotool": {"program": "dotool", "args": ["{text}"], "stdin": true}},
+                "char_delays": {"\n": 250}
+            }"#,
+        )
+        .unwrap();
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        for format in [Format::Json, Format::Toml, Format::Yaml] {
+            let content = comb.to_format(format).unwrap();
+            let path = dir.join(format!("config.{format}"));
+            fs::write(&path, &content).unwrap();
+            let mut loaded = Combinations::from_path(&path).unwrap();
+            loaded.sources.clear();
+            assert_eq!(loaded, comb, "{content}");
+        }
+
+        let path = dir.join("config.toml");
+        fs::write(
+            &path,
+            "[sequences]\nA = \"\"\"\nfirst\nsecond\"\"\"\n\n[combinations.X]\nsequence = \"A2\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            Combinations::from_path(&path)
+                .unwrap()
+                .get_sequence("X", &Vec::new())
+                .unwrap(),
+            "first\nsecondfirst\nsecond"
+        );
+        fs::write(&path, "[sequences\n").unwrap();
+        assert!(matches!(
+            Combinations::from_path(&path).unwrap_err().get_type(),
+            ErrType::InvalidConfig(_)
+        ));
+    }
+
+    #[test]
+    fn include() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("shared")).unwrap();
+        let write = |name: &str, content: &str| {
+            let path = dir.join(name);
+            fs::write(&path, content).unwrap();
+            path
+        };
+        write("shared/a.json", r#"{"sequences": {"A": "a"}}"#);
+        write(
+            "shared/b.yaml",
+            "sequences:\n  B: b\ncombinations:\n  AB:\n    sequence: A B2\n",
+        );
+        write(
+            "git.toml",
+            "[sequences]\nStatus = \"git status\"\nN = \"\\n\"\n\n[combinations.Run]\nsequence = \"Status N\"\n",
+        );
+        let main = write(
+            "main.json",
+            r#"{
+                "include": ["shared/*", {"path": "git.toml", "namespace": "git"}],
+                "sequences": {"C": "c"},
+                "combinations": {"X": {"sequence": "AB gitRun C"}}
+            }"#,
+        );
+        let comb = Combinations::from_path(&main).unwrap();
+        assert_eq!(
+            comb.list_all_commands(),
+            vec!["A", "AB", "B", "C", "X", "gitN", "gitRun", "gitStatus"]
+        );
+        assert_eq!(
+            comb.get_sequence("X", &Vec::new()).unwrap(),
+            "abbgit status\nc"
+        );
+
+        write(
+            "shared/c.json",
+            r#"{"combinations": {"C": {"sequence": "A"}}}"#,
+        );
+        let err = Combinations::from_path(&main).unwrap_err();
+        assert_eq!(
+            err.get_type(),
+            &ErrType::KeyIsInSequences(String::from("C"))
+        );
+        assert_eq!(
+            err.get_message(),
+            Some(&format!(
+                "({} and {})",
+                main.display(),
+                dir.join("shared/c.json").display()
+            ))
+        );
+        fs::remove_file(dir.join("shared/c.json")).unwrap();
+
+        write("shared/c.json", r#"{"include": ["../main.json"]}"#);
+        assert_eq!(
+            Combinations::from_path(&main).unwrap_err().get_type(),
+            &ErrType::IncludeCycle(dir.join("shared/../main.json").display().to_string())
+        );
+
+        let missing = write("missing.json", r#"{"include": ["none.json"]}"#);
+        assert!(matches!(
+            Combinations::from_path(&missing).unwrap_err().get_type(),
+            ErrType::InvalidConfig(_)
+        ));
+    }
+
+    #[test]
+    fn profile_overrides_include() {
+        let tmp = TempDir::new().unwrap();
+        let shared = tmp.path().join("shared.json");
+        fs::write(&shared, r#"{"sequences": {"Sig": "Regards"}}"#).unwrap();
+        let main = tmp.path().join("main.json");
+        fs::write(
+            &main,
+            r#"{
+                "include": ["shared.json"],
+                "sequences": {"Name": "John"},
+                "profiles": {"work": {
+                    "sequences": {"Sig": "Best regards"},
+                    "combinations": {"X": {"sequence": "Sig Missing"}}
+                }}
+            }"#,
+        )
+        .unwrap();
+        let comb = Combinations::from_path(&main).unwrap();
+        assert_eq!(comb.get_sequence("Sig", &Vec::new()).unwrap(), "Regards");
+        assert_eq!(comb.sources["Sig"], shared);
+        let work = comb.clone().with_profile("work").unwrap();
+        assert_eq!(
+            work.get_sequence("Sig", &Vec::new()).unwrap(),
+            "Best regards"
+        );
+        let errors = comb.get_profile_errors("work").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].get_location().unwrap().get_file(),
+            Some(main.as_path())
+        );
+    }
 }
@@ -3,6 +3,13 @@ use rand::Rng;
 use std::fmt::{self, Display};
 use std::{ops::Range, str::FromStr};
 
+/// Names of subcommands. Sequences and combinations with these names
+/// are typed only after `--`, otherwise the subcommand is run.
+pub const RESERVED_NAMES: &[&str] = &[
+    "add", "check", "convert", "export", "help", "import", "migrate", "remove", "rename", "schema",
+    "stop",
+];
+
 #[derive(Debug, PartialEq, Eq)]
 enum Times {
     Number(usize),
@@ -117,6 +124,21 @@ impl Command {
         }
     }
 
+    /// Return the smallest possible number of repetitions of command.
+    ///
+    /// ```
+    /// # use shortcut_autotyper::Command;
+    /// assert_eq!(Command::new_range("A", 3..5).get_min_times(), 3);
+    /// assert_eq!(Command::new("A").get_min_times(), 1);
+    /// ```
+    pub fn get_min_times(&self) -> usize {
+        match &self.times {
+            Some(Times::Number(n)) => *n,
+            Some(Times::Range(r)) => r.start,
+            None => 1,
+        }
+    }
+
     /// Return reference to name of the command.
     pub fn get_name(&self) -> &str {
         &self.name
@@ -0,0 +1,395 @@
+use crate::{
+    combinations::Combinations,
+    error::{ErrAutoType, ErrType},
+    format::Format,
+};
+use clap::ValueEnum;
+use std::{
+    env::{split_paths, var_os},
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const APP_NAME: &str = "shortcut-autotyper";
+const CONFIG_NAME: &str = "config";
+const LEGACY_CONFIG: &str = ".shortcut_autotyper.json";
+const DEFAULT_CONFIG_DIRS: &str = "/etc/xdg";
+const BACKUP_EXTENSION: &str = "bak";
+const TMP_EXTENSION: &str = "tmp";
+
+/// Locations of configuration files by XDG base directory specification.
+/// User configuration is `$XDG_CONFIG_HOME/shortcut-autotyper/config.*`,
+/// or `~/.shortcut_autotyper.json` if it does not exist. System-wide
+/// configurations are searched in directories from `$XDG_CONFIG_DIRS`.
+#[derive(Debug, Default, Clone)]
+pub struct ConfigLocations {
+    home: Option<PathBuf>,
+    config_home: Option<PathBuf>,
+    config_dirs: Option<OsString>,
+}
+
+impl ConfigLocations {
+    /// Create [`ConfigLocations`] from `HOME`, `XDG_CONFIG_HOME`
+    /// and `XDG_CONFIG_DIRS` environment variables.
+    pub fn from_env() -> ConfigLocations {
+        let non_empty = |name| var_os(name).filter(|value| !value.is_empty());
+        ConfigLocations {
+            home: non_empty("HOME").map(PathBuf::from),
+            config_home: non_empty("XDG_CONFIG_HOME").map(PathBuf::from),
+            config_dirs: non_empty("XDG_CONFIG_DIRS"),
+        }
+    }
+
+    /// Expand `~` at the start of `path` to home directory.
+    ///
+    /// ```
+    /// # use shortcut_autotyper::config::ConfigLocations;
+    /// # use std::path::PathBuf;
+    /// let locations = ConfigLocations::from_env();
+    /// assert_eq!(locations.expand("/etc/config.json"), PathBuf::from("/etc/config.json"));
+    /// assert_eq!(locations.expand("~user/config.json"), PathBuf::from("~user/config.json"));
+    /// ```
+    pub fn expand(&self, path: &str) -> PathBuf {
+        match (path.strip_prefix('~'), &self.home) {
+            (Some(""), Some(home)) => home.clone(),
+            (Some(rest), Some(home)) if rest.starts_with('/') => home.join(&rest[1..]),
+            _ => PathBuf::from(path),
+        }
+    }
+
+    /// Returns paths of configuration layers from the lowest priority.
+    /// If `explicit` path is given, only this file is used. Returns
+    /// [`ErrType::ConfigNotFound`] if there is no configuration file.
+    pub fn find(&self, explicit: Option<&str>) -> Result<Vec<PathBuf>, ErrAutoType> {
+        if let Some(path) = explicit {
+            return Ok(vec![self.expand(path)]);
+        }
+        let layers = self.layers();
+        if layers.is_empty() {
+            return ErrType::ConfigNotFound(
+                self.candidates()
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect(),
+            )
+            .into();
+        }
+        Ok(layers)
+    }
+
+    /// Returns path of configuration file edited by subcommands. It is
+    /// `explicit` path if it is given, otherwise the user configuration.
+    /// If no user configuration exists, new JSON configuration
+    /// in `$XDG_CONFIG_HOME` is used.
+    pub fn editable(&self, explicit: Option<&str>) -> Result<PathBuf, ErrAutoType> {
+        if let Some(path) = explicit {
+            return Ok(self.expand(path));
+        }
+        let user = self.user_dir().and_then(|dir| find_config(&dir));
+        user.or_else(|| self.legacy().filter(|path| path.is_file()))
+            .or_else(|| Some(self.user_dir()?.join(CONFIG_NAME).with_extension("json")))
+            .ok_or_else(|| ErrType::ConfigNotFound(Vec::new()).into())
+    }
+
+    /// Returns existing configuration files from the lowest priority,
+    /// system-wide configurations are followed by user configuration.
+    pub fn layers(&self) -> Vec<PathBuf> {
+        let mut layers = self
+            .system_dirs()
+            .iter()
+            .rev()
+            .filter_map(|dir| find_config(dir))
+            .collect::<Vec<_>>();
+        let user = self.user_dir().and_then(|dir| find_config(&dir));
+        layers.extend(user.or_else(|| self.legacy().filter(|path| path.is_file())));
+        layers
+    }
+
+    /// Directory with user configuration.
+    fn user_dir(&self) -> Option<PathBuf> {
+        let config_home = match &self.config_home {
+            Some(dir) => dir.clone(),
+            None => self.home.as_ref()?.join(".config"),
+        };
+        Some(config_home.join(APP_NAME))
+    }
+
+    /// Directories with system-wide configuration from the highest priority.
+    fn system_dirs(&self) -> Vec<PathBuf> {
+        let dirs = self
+            .config_dirs
+            .clone()
+            .unwrap_or_else(|| OsString::from(DEFAULT_CONFIG_DIRS));
+        split_paths(&dirs)
+            .filter(|dir| dir.is_absolute())
+            .map(|dir| dir.join(APP_NAME))
+            .collect()
+    }
+
+    /// Configuration file used before XDG directories were supported.
+    fn legacy(&self) -> Option<PathBuf> {
+        Some(self.home.as_ref()?.join(LEGACY_CONFIG))
+    }
+
+    /// All paths, where configuration is searched for.
+    fn candidates(&self) -> Vec<PathBuf> {
+        self.user_dir()
+            .into_iter()
+            .chain(self.system_dirs())
+            .flat_map(|dir| config_files(&dir))
+            .chain(self.legacy())
+            .collect()
+    }
+}
+
+/// Possible configuration files in `dir` in order of preference.
+fn config_files(dir: &Path) -> Vec<PathBuf> {
+    Format::value_variants()
+        .iter()
+        .flat_map(Format::extensions)
+        .map(|ext| dir.join(CONFIG_NAME).with_extension(ext))
+        .collect()
+}
+
+/// Find the first existing configuration file in `dir`.
+fn find_config(dir: &Path) -> Option<PathBuf> {
+    config_files(dir).into_iter().find(|file| file.is_file())
+}
+
+/// Load configuration files in `paths` same as [`load_unchecked()`],
+/// apply `profile` if it is set and validate the result by
+/// [`Combinations::validate()`].
+pub fn load(paths: &[PathBuf], profile: Option<&str>) -> Result<Combinations, ErrAutoType> {
+    let mut combinations = load_unchecked(paths)?;
+    if let Some(profile) = profile {
+        combinations = combinations.with_profile(profile)?;
+    }
+    combinations.validate()?;
+    Ok(combinations)
+}
+
+/// Load configuration files in `paths` and merge each over the previous
+/// one. See [`Combinations::merge()`].
+pub fn load_unchecked(paths: &[PathBuf]) -> Result<Combinations, ErrAutoType> {
+    let mut layers = paths.iter().map(|path| Combinations::from_path(path));
+    let mut combinations = match layers.next() {
+        Some(layer) => layer?,
+        None => return ErrType::ConfigNotFound(Vec::new()).into(),
+    };
+    for layer in layers {
+        combinations.merge(layer?);
+    }
+    Ok(combinations)
+}
+
+/// Write `combinations` to `path` in format selected by its extension.
+/// Previous content of the file is kept in `<path>.bak` and the file
+/// is replaced atomically, so it is never left partially written.
+pub fn save(path: &Path, combinations: &Combinations) -> Result<(), ErrAutoType> {
+    let content = combinations.to_format(Format::from_path(path)?)?;
+    let failed = |e: std::io::Error| {
+        ErrAutoType::new_with_message(
+            ErrType::CannotSave(path.display().to_string()),
+            e.to_string(),
+        )
+    };
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(failed)?;
+    }
+    if path.is_file() {
+        fs::copy(path, with_suffix(path, BACKUP_EXTENSION)).map_err(failed)?;
+    }
+    let tmp = with_suffix(path, TMP_EXTENSION);
+    fs::write(&tmp, content).map_err(failed)?;
+    fs::rename(&tmp, path).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        failed(e)
+    })
+}
+
+/// Upgrade configuration file on `path` to current version and save it
+/// by [`save()`], if it changed. Included files are not migrated.
+/// Returns descriptions of all changes.
+pub fn migrate(path: &Path) -> Result<Vec<String>, ErrAutoType> {
+    let (combinations, changes) = Combinations::read_migrated(path)?;
+    if !changes.is_empty() {
+        save(path, &combinations)?;
+    }
+    Ok(changes)
+}
+
+/// Append `.suffix` to file name of `path`.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn locations(dir: &Path, config_home: bool) -> ConfigLocations {
+        let dirs = [
+            dir.join("xdg-a"),
+            dir.join("xdg-b"),
+            PathBuf::from("relative"),
+        ];
+        ConfigLocations {
+            home: Some(dir.join("home")),
+            config_home: config_home.then(|| dir.join("config")),
+            config_dirs: Some(std::env::join_paths(dirs).unwrap()),
+        }
+    }
+
+    #[test]
+    fn expand() {
+        let locations = ConfigLocations {
+            home: Some(PathBuf::from("/home/user")),
+            ..Default::default()
+        };
+        assert_eq!(locations.expand("~"), PathBuf::from("/home/user"));
+        assert_eq!(
+            locations.expand("~/conf/a.json"),
+            PathBuf::from("/home/user/conf/a.json")
+        );
+        assert_eq!(locations.expand("a/~/b"), PathBuf::from("a/~/b"));
+        assert_eq!(
+            ConfigLocations::default().expand("~/a.json"),
+            PathBuf::from("~/a.json")
+        );
+    }
+
+    #[test]
+    fn layers() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        let locations = locations(dir, true);
+        assert_eq!(locations.layers(), Vec::<PathBuf>::new());
+        assert!(matches!(
+            locations.find(None).unwrap_err().get_type(),
+            ErrType::ConfigNotFound(candidates) if candidates.len() == 13
+        ));
+
+        let legacy = dir.join("home").join(LEGACY_CONFIG);
+        write(&legacy, "{}");
+        assert_eq!(locations.layers(), vec![legacy.clone()]);
+
+        let user = dir.join("config").join(APP_NAME).join("config.json");
+        write(&user, "{}");
+        assert_eq!(locations.layers(), vec![user.clone()]);
+        let home_user = dir.join("home/.config").join(APP_NAME).join("config.json");
+        write(&home_user, "{}");
+        assert_eq!(self::locations(dir, false).layers(), vec![home_user]);
+
+        let system_a = dir.join("xdg-a").join(APP_NAME).join("config.json");
+        let system_b = dir.join("xdg-b").join(APP_NAME).join("config.json");
+        write(&system_a, "{}");
+        write(&system_b, "{}");
+        assert_eq!(locations.layers(), vec![system_b, system_a, user.clone()]);
+        assert_eq!(
+            locations.find(Some("~/other.json")).unwrap(),
+            vec![dir.join("home").join("other.json")]
+        );
+    }
+
+    #[test]
+    fn load() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        let system = dir.join("system.json");
+        let user = dir.join("user.json");
+        write(
+            &system,
+            r#"{"sequences": {"A": "system a", "B": "system b"}}"#,
+        );
+        write(&user, r#"{"sequences": {"A": "user a"}}"#);
+        let combinations = super::load(&[system.clone(), user.clone()], None).unwrap();
+        assert_eq!(
+            combinations.get_sequence("A B", &Vec::new()).unwrap(),
+            "user asystem b"
+        );
+
+        let missing = dir.join("missing.json");
+        let err = super::load(&[system, missing.clone()], None).unwrap_err();
+        assert_eq!(
+            err.get_type(),
+            &ErrType::InvalidConfig(missing.display().to_string())
+        );
+        write(&user, "{");
+        assert!(super::load(std::slice::from_ref(&user), None).is_err());
+
+        write(
+            &user,
+            r#"{"sequences": {"A": "a"}, "combinations": {"X": {"sequence": "A C"}}}"#,
+        );
+        assert!(load_unchecked(std::slice::from_ref(&user)).is_ok());
+        assert_eq!(
+            super::load(std::slice::from_ref(&user), None).unwrap_err().get_type(),
+            &ErrType::InvalidConfigContent(vec![format!(
+                "Sequence \"C\" is not registered\n --> {}:1:65\n  |\n1 | {}\n  | {}^\n  = in \"X\"",
+                user.display(),
+                r#"{"sequences": {"A": "a"}, "combinations": {"X": {"sequence": "A C"}}}"#,
+                " ".repeat(64),
+            )])
+        );
+    }
+
+    #[test]
+    fn save() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        let path = dir.join("new").join("config.toml");
+        let mut combinations = Combinations::default();
+        combinations.insert_sequence("A", "a").unwrap();
+        super::save(&path, &combinations).unwrap();
+        assert!(!with_suffix(&path, BACKUP_EXTENSION).exists());
+
+        combinations.insert("X", "A2").unwrap();
+        super::save(&path, &combinations).unwrap();
+        assert_eq!(
+            fs::read_to_string(with_suffix(&path, BACKUP_EXTENSION)).unwrap(),
+            "version = 2\n\n[combinations]\n\n[sequences]\nA = \"a\"\n"
+        );
+        assert!(!with_suffix(&path, TMP_EXTENSION).exists());
+        let loaded = Combinations::read(&path).unwrap();
+        assert_eq!(loaded.get_sequence("X", &Vec::new()).unwrap(), "aa");
+    }
+
+    #[test]
+    fn migrate() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        let path = dir.join("config.json");
+        let legacy = r#"{"B": "b", "A": "a", "X": {"sequence": "A B"}}"#;
+        write(&path, legacy);
+        let combinations = super::load(std::slice::from_ref(&path), None).unwrap();
+        assert_eq!(combinations.get_sequence("X", &Vec::new()).unwrap(), "ab");
+
+        assert_eq!(super::migrate(&path).unwrap().len(), 4);
+        assert_eq!(
+            fs::read_to_string(with_suffix(&path, BACKUP_EXTENSION)).unwrap(),
+            legacy
+        );
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\n  \"version\": 2,\n  \"combinations\": {\n    \"X\": {\n      \"sequence\": \"A B\"\n    }\n  },\n  \"sequences\": {\n    \"B\": \"b\",\n    \"A\": \"a\"\n  }\n}\n"
+        );
+        assert_eq!(super::migrate(&path).unwrap(), Vec::<String>::new());
+
+        write(&path, r#"{"version": 100}"#);
+        assert_eq!(
+            super::migrate(&path).unwrap_err().get_type(),
+            &ErrType::UnsupportedVersion(100, 2)
+        );
+    }
+}
@@ -111,6 +111,26 @@ impl Content {
     pub fn generate_content(&self, args: &[String]) -> String {
         self.0.iter().map(|c| c.generate_content(args)).collect()
     }
+
+    /// Returns sorted indexes of variable placeholders without duplicates.
+    ///
+    /// ```
+    /// use shortcut_autotyper::Content;
+    /// assert_eq!(Content::from("<2> <1> <2> <x>").get_variables(), vec![1, 2]);
+    /// ```
+    pub fn get_variables(&self) -> Vec<usize> {
+        let mut variables = self
+            .0
+            .iter()
+            .filter_map(|c| match c {
+                ContentItem::Variable(v) => Some(*v),
+                ContentItem::Value(_) => None,
+            })
+            .collect::<Vec<_>>();
+        variables.sort();
+        variables.dedup();
+        variables
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,161 @@
+use crate::error::{ErrAutoType, ErrType};
+use rand::Rng;
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display},
+    ops::Range,
+    str::FromStr,
+};
+
+/// Delay between two key strokes in milliseconds. It can be fixed number,
+/// or range from which is delay randomly selected for every key stroke.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(try_from = "DelayDef", into = "DelayDef")]
+pub enum Delay {
+    Fixed(usize),
+    Range(Range<usize>),
+}
+
+/// Serialized form of [`Delay`], number or string with range.
+#[derive(Deserialize, Serialize, JsonSchema)]
+#[serde(untagged)]
+enum DelayDef {
+    Number(usize),
+    Text(#[schemars(regex(pattern = r"^[0-9]+\.\.[0-9]+$"))] String),
+}
+
+impl Delay {
+    /// Return delay for one key stroke, for range return one of random
+    /// possible options.
+    ///
+    /// ```
+    /// # use shortcut_autotyper::Delay;
+    /// let delay: Delay = "30..90".parse().unwrap();
+    /// let sample = delay.sample();
+    /// assert!(sample >= 30);
+    /// assert!(sample < 90);
+    /// ```
+    pub fn sample(&self) -> usize {
+        match self {
+            Delay::Fixed(n) => *n,
+            Delay::Range(r) if r.is_empty() => r.start,
+            Delay::Range(r) => rand::rng().random_range(r.start..r.end),
+        }
+    }
+
+    /// Returns `true` if delay is the same for every key stroke.
+    pub fn is_fixed(&self) -> bool {
+        matches!(self, Delay::Fixed(_))
+    }
+}
+
+impl FromStr for Delay {
+    type Err = ErrAutoType;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(delay) = s.parse::<usize>() {
+            return Ok(Delay::Fixed(delay));
+        };
+        let Some((start, end)) = s.split_once("..") else {
+            return ErrType::InvalidDelay(String::from(s)).into();
+        };
+        match (start.parse::<usize>(), end.parse::<usize>()) {
+            (Ok(start), Ok(end)) if start <= end => Ok(Delay::Range(start..end)),
+            (Ok(start), Ok(end)) => ErrType::RangeMustNotBeEmpty(start..end).into(),
+            _ => ErrType::InvalidDelay(String::from(s)).into(),
+        }
+    }
+}
+
+impl Display for Delay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Delay::Fixed(n) => write!(f, "{n}"),
+            Delay::Range(r) => write!(f, "{}..{}", r.start, r.end),
+        }
+    }
+}
+
+impl From<usize> for Delay {
+    fn from(value: usize) -> Self {
+        Delay::Fixed(value)
+    }
+}
+
+impl JsonSchema for Delay {
+    fn schema_name() -> String {
+        String::from("Delay")
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        DelayDef::json_schema(gen)
+    }
+}
+
+impl TryFrom<DelayDef> for Delay {
+    type Error = ErrAutoType;
+
+    fn try_from(value: DelayDef) -> Result<Self, Self::Error> {
+        match value {
+            DelayDef::Number(n) => Ok(Delay::Fixed(n)),
+            DelayDef::Text(s) => s.parse(),
+        }
+    }
+}
+
+impl From<Delay> for DelayDef {
+    fn from(value: Delay) -> Self {
+        match value {
+            Delay::Fixed(n) => DelayDef::Number(n),
+            delay => DelayDef::Text(delay.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str() {
+        assert_eq!("50".parse(), Ok(Delay::Fixed(50)));
+        assert_eq!("30..90".parse(), Ok(Delay::Range(30..90)));
+        assert_eq!("30..30".parse(), Ok(Delay::Range(30..30)));
+        assert!("90..30".parse::<Delay>().is_err());
+        assert_eq!(
+            "30..".parse::<Delay>(),
+            ErrType::InvalidDelay(String::from("30..")).into()
+        );
+        assert_eq!(
+            "fast".parse::<Delay>(),
+            ErrType::InvalidDelay(String::from("fast")).into()
+        );
+    }
+
+    #[test]
+    fn sample() {
+        assert_eq!(Delay::Fixed(50).sample(), 50);
+        assert_eq!(Delay::Range(30..30).sample(), 30);
+        for _ in 0..100 {
+            let sample = Delay::Range(30..90).sample();
+            assert!(sample >= 30);
+            assert!(sample < 90);
+        }
+    }
+
+    #[test]
+    fn de_serialization() {
+        assert_eq!(serde_json::from_str("50").ok(), Some(Delay::Fixed(50)));
+        assert_eq!(
+            serde_json::from_str(r#""30..90""#).ok(),
+            Some(Delay::Range(30..90))
+        );
+        assert!(serde_json::from_str::<Delay>(r#""90..30""#).is_err());
+        assert_eq!(serde_json::to_string(&Delay::Fixed(50)).unwrap(), "50");
+        assert_eq!(
+            serde_json::to_string(&Delay::Range(30..90)).unwrap(),
+            r#""30..90""#
+        );
+    }
+}
@@ -1,6 +1,27 @@
+use serde_json::{json, Value};
 use std::error::Error;
 use std::fmt::{self, Display};
 use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Exit code of unexpected errors.
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit code of invalid arguments, same as of arguments rejected by parser.
+pub const EXIT_USAGE: i32 = 2;
+/// Exit code if sequence or combination is not found.
+pub const EXIT_NOT_FOUND: i32 = 3;
+/// Exit code if configuration is invalid or cannot be loaded.
+pub const EXIT_CONFIG: i32 = 4;
+/// Exit code if no typer is usable or typer fails.
+pub const EXIT_TYPER: i32 = 5;
+/// Exit code if typing was interrupted.
+pub const EXIT_INTERRUPTED: i32 = 6;
+/// Exit code if another typing session is running or none is running.
+pub const EXIT_SESSION: i32 = 7;
+/// Exit code if file cannot be read, written or converted.
+pub const EXIT_IO: i32 = 8;
+/// Exit code if secret cannot be fetched from its provider.
+pub const EXIT_SECRET: i32 = 9;
 
 /// Type definition for single [`ErrAutoType`].
 pub type ATResult<T> = Result<T, ErrAutoType>;
@@ -21,13 +42,51 @@ pub enum ErrType {
     KeyIsInCombinations(String),
     RangeMustNotBeEmpty(Range<usize>),
     ArgumentMissing(String),
+    NoTyperAvailable(Vec<String>),
+    UnknownTyper(String),
+    TyperFailed(String),
+    FocusNotChanged(usize),
+    InvalidDelay(String),
+    Interrupted(usize, usize),
+    SessionRunning(u32),
+    NoSessionRunning,
+    ConfigNotFound(Vec<String>),
+    InvalidConfig(String),
+    UnknownFormat(String),
+    CannotSerialize(String),
+    InvalidInclude(String),
+    IncludeCycle(String),
+    CombinationCycle(String),
+    InvalidConfigContent(Vec<String>),
+    CannotSave(String),
+    KeyIsReferenced(String, Vec<String>),
+    UnsupportedVersion(u32, u32),
+    InvalidImport(String),
+    UnknownProfile(String),
+    SecretUnavailable(String),
 }
 
-/// Main error type for [`crate`]. It's [`ErrType`] with optional additional message.
+/// Main error type for [`crate`]. It's [`ErrType`] with optional additional
+/// message and [`Location`] in configuration.
 #[derive(Debug)]
 pub struct ErrAutoType {
     err_type: ErrType,
     message: Option<String>,
+    location: Option<Box<Location>>,
+}
+
+/// Place in configuration, where error occurred. It is an entry
+/// of sequences or combinations and optionally byte span in its text.
+/// If configuration file is known, the place is found in its content
+/// and rendered with the line of the file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Location {
+    entry: Option<String>,
+    span: Option<Range<usize>>,
+    file: Option<PathBuf>,
+    position: Option<(usize, usize)>,
+    line: Option<String>,
+    width: usize,
 }
 
 impl Display for ErrType {
@@ -43,6 +102,126 @@ impl Display for ErrType {
             KeyIsInCombinations(s) => write!(f, "Key \"{s}\" is now in combinations."),
             RangeMustNotBeEmpty(r) => write!(f, "Range \"{}..{}\" is empty.", r.start, r.end),
             ArgumentMissing(a) => write!(f, "Missing value for argument: {}", a),
+            NoTyperAvailable(t) => write!(f, "No usable typer found, tried: {}", t.join("; ")),
+            UnknownTyper(t) => write!(f, "Typer \"{t}\" is not defined"),
+            TyperFailed(t) => write!(f, "Typer \"{t}\" failed"),
+            FocusNotChanged(t) => write!(f, "Active window did not change in {t} ms"),
+            InvalidDelay(d) => write!(f, "Delay \"{d}\" have wrong format"),
+            SessionRunning(pid) => write!(f, "Typing session is already running (PID {pid})"),
+            NoSessionRunning => write!(f, "No typing session is running"),
+            ConfigNotFound(c) => write!(f, "No configuration file found, tried: {}", c.join(", ")),
+            InvalidConfig(c) => write!(f, "Configuration file \"{c}\" cannot be loaded:"),
+            UnknownFormat(p) => write!(f, "Format of \"{p}\" is not known by extension"),
+            CannotSerialize(c) => write!(f, "Cannot serialize to {c}:"),
+            InvalidInclude(i) => write!(f, "Include \"{i}\" is invalid:"),
+            IncludeCycle(i) => write!(f, "Configuration file \"{i}\" includes itself"),
+            CombinationCycle(c) => write!(f, "Combination \"{c}\" references itself"),
+            CannotSave(c) => write!(f, "Configuration file \"{c}\" cannot be saved:"),
+            UnknownProfile(p) => write!(f, "Profile \"{p}\" is not defined"),
+            SecretUnavailable(s) => write!(f, "Secret of \"{s}\" cannot be read from"),
+            InvalidImport(i) => write!(f, "File \"{i}\" cannot be imported:"),
+            UnsupportedVersion(v, c) => {
+                write!(
+                    f,
+                    "Configuration version {v} is newer than supported version {c}"
+                )
+            }
+            KeyIsReferenced(k, c) => {
+                write!(f, "Key \"{k}\" is referenced by: {}", c.join(", "))
+            }
+            InvalidConfigContent(e) => {
+                write!(f, "Configuration is not valid:\n{}", e.join("\n"))
+            }
+            Interrupted(typed, total) => {
+                write!(f, "Typing interrupted after {typed} of {total} characters")
+            }
+        }
+    }
+}
+
+impl ErrType {
+    /// Returns exit code of the program for the error.
+    pub fn exit_code(&self) -> i32 {
+        use ErrType::*;
+        match self {
+            WrongSequenceArg(_) | ArgumentMissing(_) | InvalidDelay(_) | UnknownProfile(_) => {
+                EXIT_USAGE
+            }
+            SequenceNotExist(_) | UnknownSequence(_) => EXIT_NOT_FOUND,
+            InvalidKeyFormat(_)
+            | KeyIsInSequences(_)
+            | KeyCannotBeEmpty
+            | KeyIsInCombinations(_)
+            | RangeMustNotBeEmpty(_)
+            | ConfigNotFound(_)
+            | InvalidConfig(_)
+            | InvalidInclude(_)
+            | IncludeCycle(_)
+            | CombinationCycle(_)
+            | InvalidConfigContent(_)
+            | KeyIsReferenced(_, _)
+            | UnsupportedVersion(_, _) => EXIT_CONFIG,
+            NoTyperAvailable(_) | UnknownTyper(_) | TyperFailed(_) | FocusNotChanged(_) => {
+                EXIT_TYPER
+            }
+            Interrupted(_, _) => EXIT_INTERRUPTED,
+            SessionRunning(_) | NoSessionRunning => EXIT_SESSION,
+            UnknownFormat(_) | CannotSerialize(_) | CannotSave(_) | InvalidImport(_) => EXIT_IO,
+            SecretUnavailable(_) => EXIT_SECRET,
+        }
+    }
+
+    /// Returns name of the error type used in machine-readable output.
+    pub fn name(&self) -> &'static str {
+        use ErrType::*;
+        match self {
+            SequenceNotExist(_) => "sequence_not_exist",
+            WrongSequenceArg(_) => "wrong_sequence_arg",
+            InvalidKeyFormat(_) => "invalid_key_format",
+            KeyIsInSequences(_) => "key_is_in_sequences",
+            KeyCannotBeEmpty => "key_cannot_be_empty",
+            UnknownSequence(_) => "unknown_sequence",
+            KeyIsInCombinations(_) => "key_is_in_combinations",
+            RangeMustNotBeEmpty(_) => "range_must_not_be_empty",
+            ArgumentMissing(_) => "argument_missing",
+            NoTyperAvailable(_) => "no_typer_available",
+            UnknownTyper(_) => "unknown_typer",
+            TyperFailed(_) => "typer_failed",
+            FocusNotChanged(_) => "focus_not_changed",
+            InvalidDelay(_) => "invalid_delay",
+            Interrupted(_, _) => "interrupted",
+            SessionRunning(_) => "session_running",
+            NoSessionRunning => "no_session_running",
+            ConfigNotFound(_) => "config_not_found",
+            InvalidConfig(_) => "invalid_config",
+            UnknownFormat(_) => "unknown_format",
+            CannotSerialize(_) => "cannot_serialize",
+            InvalidInclude(_) => "invalid_include",
+            IncludeCycle(_) => "include_cycle",
+            CombinationCycle(_) => "combination_cycle",
+            InvalidConfigContent(_) => "invalid_config_content",
+            CannotSave(_) => "cannot_save",
+            KeyIsReferenced(_, _) => "key_is_referenced",
+            UnsupportedVersion(_, _) => "unsupported_version",
+            InvalidImport(_) => "invalid_import",
+            UnknownProfile(_) => "unknown_profile",
+            SecretUnavailable(_) => "secret_unavailable",
+        }
+    }
+
+    /// Returns key of sequence or combination the error is about.
+    pub fn key(&self) -> Option<&str> {
+        use ErrType::*;
+        match self {
+            SequenceNotExist(k)
+            | InvalidKeyFormat(k)
+            | KeyIsInSequences(k)
+            | UnknownSequence(k)
+            | KeyIsInCombinations(k)
+            | CombinationCycle(k)
+            | KeyIsReferenced(k, _)
+            | SecretUnavailable(k) => Some(k),
+            _ => None,
         }
     }
 }
@@ -57,10 +236,7 @@ impl Error for ErrAutoType {}
 
 impl Display for ErrAutoType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match &self.message {
-            Some(m) => write!(f, "Error: {} {}", self.err_type, m),
-            None => write!(f, "Error: {}", self.err_type),
-        }
+        write!(f, "Error: {}", self.describe())
     }
 }
 
@@ -81,6 +257,7 @@ impl ErrAutoType {
         ErrAutoType {
             err_type,
             message: None,
+            location: None,
         }
     }
 
@@ -88,9 +265,38 @@ impl ErrAutoType {
         ErrAutoType {
             err_type,
             message: Some(msg),
+            location: None,
         }
     }
 
+    /// Attach `location` to the error.
+    pub fn with_location(mut self, location: Location) -> ErrAutoType {
+        self.location = Some(Box::new(location));
+        self
+    }
+
+    /// Returns mutable reference to location of the error.
+    pub fn get_location_mut(&mut self) -> Option<&mut Location> {
+        self.location.as_deref_mut()
+    }
+
+    pub fn get_location(&self) -> Option<&Location> {
+        self.location.as_deref()
+    }
+
+    /// Returns description of the error with message and rendered
+    /// location, without `Error:` prefix.
+    pub fn describe(&self) -> String {
+        let mut description = self.err_type.to_string();
+        if let Some(message) = &self.message {
+            description += &format!(" {message}");
+        }
+        if let Some(location) = &self.location {
+            description += &location.to_string();
+        }
+        description
+    }
+
     pub fn get_type(&self) -> &ErrType {
         &self.err_type
     }
@@ -98,4 +304,228 @@ impl ErrAutoType {
     pub fn get_message(&self) -> Option<&String> {
         self.message.as_ref()
     }
+
+    /// Returns the error as JSON object with type, exit code, key, message
+    /// and position in configuration file if it is known.
+    pub fn to_json(&self) -> Value {
+        let location = self.get_location();
+        let mut message = self.err_type.to_string();
+        if let Some(m) = &self.message {
+            message += &format!(" {m}");
+        }
+        let position = location.and_then(Location::get_position);
+        json!({
+            "type": self.err_type.name(),
+            "code": self.err_type.exit_code(),
+            "key": self.err_type.key().or(location.and_then(Location::get_entry)),
+            "message": message,
+            "file": location.and_then(Location::get_file),
+            "line": position.map(|(line, _)| line),
+            "column": position.map(|(_, column)| column),
+        })
+    }
+}
+
+impl Location {
+    /// Create [`Location`] of sequence or combination `entry`.
+    pub fn entry(entry: &str) -> Location {
+        Location {
+            entry: Some(String::from(entry)),
+            ..Default::default()
+        }
+    }
+
+    /// Create [`Location`] in `file` on `line` and `column`, both counted from 1.
+    pub fn position(file: &Path, content: &str, line: usize, column: usize) -> Location {
+        Location {
+            file: Some(file.to_path_buf()),
+            position: Some((line, column)),
+            line: content
+                .lines()
+                .nth(line.saturating_sub(1))
+                .map(String::from),
+            width: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Set byte `span` of error in text of the entry.
+    pub fn with_span(mut self, span: Range<usize>) -> Location {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn get_entry(&self) -> Option<&str> {
+        self.entry.as_deref()
+    }
+
+    pub fn get_span(&self) -> Option<&Range<usize>> {
+        self.span.as_ref()
+    }
+
+    pub fn get_file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Returns line and column of the error in file, both counted from 1.
+    pub fn get_position(&self) -> Option<(usize, usize)> {
+        self.position
+    }
+
+    /// Find the entry in `content` of configuration `file`, where `text`
+    /// is text of the entry containing span. Position of the entry key is
+    /// used if the text is not found.
+    pub fn find_in(&mut self, file: &Path, content: &str, text: Option<&str>) {
+        self.file = Some(file.to_path_buf());
+        let Some(entry) = &self.entry else {
+            return;
+        };
+        let Some(key) = find_key(content, entry) else {
+            return;
+        };
+        let (start, width) = match (text, &self.span) {
+            (Some(text), Some(span)) if !text.is_empty() => match content[key..].find(text) {
+                Some(i) => (
+                    key + i + span.start,
+                    text[span.clone()].chars().count().max(1),
+                ),
+                None => (key, entry.chars().count()),
+            },
+            _ => (key, entry.chars().count()),
+        };
+        let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = content[start..]
+            .find('\n')
+            .map_or(content.len(), |i| start + i);
+        self.position = Some((
+            content[..start].matches('\n').count() + 1,
+            content[line_start..start].chars().count() + 1,
+        ));
+        self.line = Some(String::from(&content[line_start..line_end]));
+        self.width = width;
+    }
+}
+
+/// Find byte offset of `key` used as a key of map in `content`
+/// of configuration in any supported format.
+fn find_key(content: &str, key: &str) -> Option<usize> {
+    content.match_indices(key).map(|(i, _)| i).find(|i| {
+        let before = content[..*i].chars().next_back();
+        let after = content[i + key.len()..]
+            .trim_start_matches(['"', '\''])
+            .trim_start_matches([' ', '\t'])
+            .chars()
+            .next();
+        matches!(
+            before,
+            None | Some('"' | '\'' | '.' | '[' | ' ' | '\t' | '\n')
+        ) && matches!(after, Some(':' | '=' | ']'))
+    })
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.file, self.position, &self.line) {
+            (Some(file), Some((line, column)), Some(source)) => {
+                let margin = " ".repeat(line.to_string().len());
+                write!(f, "\n{margin}--> {}:{line}:{column}", file.display())?;
+                write!(f, "\n{margin} |\n{line} | {source}")?;
+                let indent = source
+                    .chars()
+                    .take(column - 1)
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect::<String>();
+                write!(f, "\n{margin} | {indent}{}", "^".repeat(self.width))?;
+                if let Some(entry) = &self.entry {
+                    write!(f, "\n{margin} = in \"{entry}\"")?;
+                }
+                Ok(())
+            }
+            (file, _, _) => {
+                if let Some(entry) = &self.entry {
+                    write!(f, " (in \"{entry}\"")?;
+                    if let Some(file) = file {
+                        write!(f, " in {}", file.display())?;
+                    }
+                    write!(f, ")")?;
+                } else if let Some(file) = file {
+                    write!(f, " (in {})", file.display())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_key() {
+        let json = r#"{"sequences": {"A": "XA"}, "combinations": {"XA": {"sequence": "A"}}}"#;
+        assert_eq!(super::find_key(json, "A"), Some(16));
+        assert_eq!(super::find_key(json, "XA"), Some(45));
+        assert_eq!(super::find_key("[combinations.X]\nX = 1", "X"), Some(14));
+        assert_eq!(super::find_key("sequences:\n  A: a\n", "A"), Some(13));
+        assert_eq!(super::find_key("AB: a", "A"), None);
+    }
+
+    #[test]
+    fn to_json() {
+        let content = "sequences:\n  A: a\ncombinations:\n  X:\n    sequence: A2 \tB~3\n";
+        let mut location = Location::entry("X").with_span(4..6);
+        location.find_in(Path::new("config.yaml"), content, Some("A2 \tB~3"));
+        let err = ErrAutoType::new_with_message(
+            ErrType::UnknownSequence(String::from("B")),
+            String::from("(typo)"),
+        )
+        .with_location(location);
+        assert_eq!(
+            err.to_json(),
+            json!({
+                "type": "unknown_sequence",
+                "code": EXIT_NOT_FOUND,
+                "key": "B",
+                "message": "Sequence \"B\" is not registered (typo)",
+                "file": "config.yaml",
+                "line": 5,
+                "column": 19,
+            })
+        );
+        let err = ErrAutoType::new(ErrType::NoSessionRunning)
+            .with_location(Location::entry("X"))
+            .to_json();
+        assert_eq!(err["key"], "X");
+        assert_eq!(err["code"], EXIT_SESSION);
+        assert_eq!(err["file"], Value::Null);
+        assert_eq!(ErrType::InvalidDelay(String::new()).exit_code(), EXIT_USAGE);
+    }
+
+    #[test]
+    fn location() {
+        let content = "sequences:\n  A: a\ncombinations:\n  X:\n    sequence: A2 \tB~3\n";
+        let mut location = Location::entry("X").with_span(4..6);
+        location.find_in(Path::new("config.yaml"), content, Some("A2 \tB~3"));
+        assert_eq!(location.get_position(), Some((5, 19)));
+        assert_eq!(
+            ErrAutoType::new(ErrType::InvalidKeyFormat(String::from("B~")))
+                .with_location(location)
+                .to_string(),
+            format!(
+                "Error: Key \"B~\" have invalid format\n --> config.yaml:5:19\n  |\n\
+                 5 |     sequence: A2 \tB~3\n  | {}\t^^\n  = in \"X\"",
+                " ".repeat(17)
+            )
+        );
+
+        let mut location = Location::entry("Y");
+        location.find_in(Path::new("config.yaml"), content, None);
+        assert_eq!(location.get_position(), None);
+        assert_eq!(location.to_string(), " (in \"Y\" in config.yaml)");
+        assert_eq!(
+            Location::position(Path::new("a.json"), "{\n}", 2, 1).to_string(),
+            "\n --> a.json:2:1\n  |\n2 | }\n  | ^"
+        );
+    }
 }
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+/// Marker of cursor position after expansion.
+pub const CURSOR: &str = "$|$";
+
+/// Espanso match file, only parts used by import and export.
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct EspansoFile {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub global_vars: Vec<EspansoVar>,
+    #[serde(default)]
+    pub matches: Vec<EspansoMatch>,
+}
+
+/// Single match with trigger and replacement text.
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct EspansoMatch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub triggers: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replace: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub markdown: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub html: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub form: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_path: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub vars: Vec<EspansoVar>,
+}
+
+/// Variable used in replacement text as `{{name}}`.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EspansoVar {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "serde_yaml::Value::is_null")]
+    pub params: serde_yaml::Value,
+}
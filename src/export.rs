@@ -0,0 +1,377 @@
+use crate::{
+    combinations::{Combinations, Kind, ListOrder},
+    command::Command,
+    error::ATResult,
+    espanso::{EspansoFile, EspansoMatch, EspansoVar},
+    format::Format,
+};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Title of exported cheat sheets.
+const TITLE: &str = "Shortcuts";
+
+/// Prefix of espanso triggers.
+const TRIGGER_PREFIX: &str = ":";
+
+/// Format of exported sequences and combinations.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Markdown cheat sheet with table of all entries.
+    Markdown,
+    /// HTML cheat sheet with table of all entries.
+    Html,
+    /// Espanso match file in YAML.
+    Espanso,
+}
+
+/// Machine-readable format of listed sequences and combinations.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListFormat {
+    /// JSON array of entries.
+    Json,
+    /// Tab-separated values with header.
+    Tsv,
+}
+
+/// Entry of cheat sheet or listing.
+#[derive(Serialize)]
+struct Row {
+    name: String,
+    kind: Kind,
+    delay: Option<String>,
+    hidden: bool,
+    arguments: Vec<usize>,
+    expansion: String,
+}
+
+/// Render all entries of `combinations` in `format`. Returns rendered
+/// content and report of entries which were not exported. Hidden entries
+/// are left out of cheat sheets, but they are exported to espanso,
+/// because other matches may reference them.
+pub fn export(
+    combinations: &Combinations,
+    format: ExportFormat,
+) -> ATResult<(String, Vec<String>)> {
+    let names = combinations.list_all_commands();
+    let visible = names
+        .into_iter()
+        .filter(|name| !combinations.is_hidden(name));
+    let rows = || rows(combinations, visible.clone());
+    match format {
+        ExportFormat::Markdown => Ok((markdown(&rows()), Vec::new())),
+        ExportFormat::Html => Ok((html(&rows()), Vec::new())),
+        ExportFormat::Espanso => espanso(combinations),
+    }
+}
+
+/// Render all entries of `combinations` in `order` as `format` for launchers
+/// and scripts. Entries contain name, kind, delay, hidden flag, indexes
+/// of required arguments and symbolic expansion.
+pub fn list(combinations: &Combinations, order: ListOrder, format: ListFormat) -> ATResult<String> {
+    let names = combinations
+        .list_commands(order)
+        .into_iter()
+        .map(|(_, name)| name);
+    let rows = rows(combinations, names);
+    match format {
+        ListFormat::Json => Format::Json.serialize(&rows),
+        ListFormat::Tsv => Ok(tsv(&rows)),
+    }
+}
+
+/// Collect rows of entries `names`.
+fn rows<'a, I: IntoIterator<Item = &'a String>>(combinations: &Combinations, names: I) -> Vec<Row> {
+    names
+        .into_iter()
+        .filter_map(|name| {
+            Some(Row {
+                name: name.clone(),
+                kind: combinations.get_kind(name)?,
+                delay: combinations
+                    .get_delay(&Command::new(name))
+                    .map(ToString::to_string),
+                hidden: combinations.is_hidden(name),
+                arguments: combinations.get_arguments(name),
+                expansion: combinations.get_expansion(name)?,
+            })
+        })
+        .collect()
+}
+
+/// Render `rows` as tab-separated values. Tabs, newlines and backslashes
+/// in fields are escaped by backslash.
+fn tsv(rows: &[Row]) -> String {
+    let field = |text: &str| {
+        text.replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+    };
+    let mut result = String::from("name\tkind\tdelay\thidden\targuments\texpansion\n");
+    for row in rows {
+        let arguments = row.arguments.iter().map(ToString::to_string);
+        result += &format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\n",
+            field(&row.name),
+            row.kind,
+            row.delay.as_deref().unwrap_or_default(),
+            row.hidden,
+            arguments.collect::<Vec<_>>().join(","),
+            field(&row.expansion),
+        );
+    }
+    result
+}
+
+/// Render `rows` as Markdown table.
+fn markdown(rows: &[Row]) -> String {
+    let cell = |text: &str| text.replace('|', "\\|").replace('\n', "\\n");
+    let mut result =
+        format!("# {TITLE}\n\n| Name | Kind | Delay | Expansion |\n| --- | --- | --- | --- |\n");
+    for row in rows {
+        let expansion = cell(&row.expansion);
+        let fence = if expansion.contains('`') { "``" } else { "`" };
+        result += &format!(
+            "| `{}` | {} | {} | {fence} {expansion} {fence} |\n",
+            row.name,
+            row.kind,
+            row.delay.as_deref().unwrap_or_default()
+        );
+    }
+    result
+}
+
+/// Render `rows` as HTML document with table.
+fn html(rows: &[Row]) -> String {
+    let mut result = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{TITLE}</title>\n</head>\n<body>\n<h1>{TITLE}</h1>\n<table>\n\
+         <tr><th>Name</th><th>Kind</th><th>Delay</th><th>Expansion</th></tr>\n"
+    );
+    for row in rows {
+        result += &format!(
+            "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td><code>{}</code></td></tr>\n",
+            escape_html(&row.name),
+            row.kind,
+            escape_html(row.delay.as_deref().unwrap_or_default()),
+            escape_html(&row.expansion).replace('\n', "<br>"),
+        );
+    }
+    result + "</table>\n</body>\n</html>\n"
+}
+
+/// Escape characters with special meaning in HTML.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Render entries as espanso matches triggered by `:name`. Placeholders
+/// of sequences become form fields and combinations reference other
+/// matches by variables of type `match`. Ranges of repetitions are
+/// exported with their lower bound. Secret sequences and combinations
+/// using them are skipped, espanso would store them in plain text.
+fn espanso(combinations: &Combinations) -> ATResult<(String, Vec<String>)> {
+    let (mut matches, mut report) = (Vec::new(), Vec::new());
+    for name in combinations.list_all_commands() {
+        if combinations.uses_secret(name) {
+            report.push(format!("\"{name}\" skipped, it types a secret"));
+            continue;
+        }
+        let (Some(kind), Some(expansion)) = (
+            combinations.get_kind(name),
+            combinations.get_expansion(name),
+        ) else {
+            continue;
+        };
+        let mut m = EspansoMatch {
+            trigger: Some(format!("{TRIGGER_PREFIX}{name}")),
+            ..Default::default()
+        };
+        match kind {
+            Kind::Sequence => match to_form(&expansion) {
+                Some(form) => m.form = Some(form),
+                None => m.replace = Some(expansion),
+            },
+            Kind::Combination => {
+                let commands = expansion
+                    .split_whitespace()
+                    .map(Command::from_str)
+                    .collect::<ATResult<Vec<_>>>()?;
+                let mut replace = String::new();
+                for command in commands.iter() {
+                    let var = command.get_name();
+                    replace += &format!("{{{{{var}}}}}").repeat(command.get_min_times());
+                    if m.vars.iter().all(|v| v.name != var) {
+                        m.vars.push(match_var(var));
+                    }
+                }
+                m.replace = Some(replace);
+            }
+        }
+        matches.push(m);
+    }
+    let content = Format::Yaml.serialize(&EspansoFile {
+        matches,
+        ..Default::default()
+    })?;
+    Ok((content, report))
+}
+
+/// Variable of type `match` expanding to match of entry `name`.
+fn match_var(name: &str) -> EspansoVar {
+    let mut params = serde_yaml::Mapping::new();
+    params.insert("trigger".into(), format!("{TRIGGER_PREFIX}{name}").into());
+    EspansoVar {
+        name: String::from(name),
+        kind: String::from("match"),
+        params: params.into(),
+    }
+}
+
+/// Convert placeholders `<n>` in `text` to form fields `[[argn]]`. Returns
+/// [`None`] if there are no placeholders.
+fn to_form(text: &str) -> Option<String> {
+    let mut result = String::new();
+    let mut rest = text;
+    let mut found = false;
+    while let Some(start) = rest.find('<') {
+        result += &rest[..start];
+        let after = &rest[start + 1..];
+        let digits = after.chars().take_while(char::is_ascii_digit).count();
+        if digits > 0 && after[digits..].starts_with('>') {
+            result += &format!("[[arg{}]]", &after[..digits]);
+            rest = &after[digits + 1..];
+            found = true;
+        } else {
+            result.push('<');
+            rest = after;
+        }
+    }
+    found.then(|| result + rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::{import, ImportFormat};
+
+    fn example() -> Combinations {
+        serde_json::from_str(
+            r#"{
+                "sequences": {
+                    "A": {"text": "Hello <1> | <x>", "delay": "10..20"},
+                    "B": {"text": "multi\nline `code`", "hidden": true}
+                },
+                "combinations": {"X": {"sequence": "A2 B1..3 A", "delay": 5}}
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn markdown() {
+        assert_eq!(
+            export(&example(), ExportFormat::Markdown).unwrap().0,
+            "# Shortcuts\n\n| Name | Kind | Delay | Expansion |\n| --- | --- | --- | --- |\n\
+             | `A` | sequence | 10..20 | ` Hello <1> \\| <x> ` |\n\
+             | `X` | combination | 5 | ` A2 B1..3 A ` |\n"
+        );
+    }
+
+    #[test]
+    fn html() {
+        let (html, _) = export(&example(), ExportFormat::Html).unwrap();
+        assert!(html.contains(
+            "<tr><td><code>A</code></td><td>sequence</td><td>10..20</td>\
+             <td><code>Hello &lt;1&gt; | &lt;x&gt;</code></td></tr>\n"
+        ));
+        assert!(!html.contains("<td><code>B</code></td>"));
+        assert!(html.ends_with("</table>\n</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn list() {
+        let json = super::list(&example(), ListOrder::Name, ListFormat::Json).unwrap();
+        let rows = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        assert_eq!(
+            rows[0],
+            serde_json::json!({
+                "name": "A",
+                "kind": "sequence",
+                "delay": "10..20",
+                "hidden": false,
+                "arguments": [1],
+                "expansion": "Hello <1> | <x>",
+            })
+        );
+        assert_eq!(rows[1]["delay"], serde_json::Value::Null);
+        assert_eq!(rows[1]["hidden"], true);
+        assert_eq!(rows[2]["arguments"], serde_json::json!([1]));
+
+        assert_eq!(
+            super::list(&example(), ListOrder::Kind, ListFormat::Tsv).unwrap(),
+            "name\tkind\tdelay\thidden\targuments\texpansion\n\
+             A\tsequence\t10..20\tfalse\t1\tHello <1> | <x>\n\
+             B\tsequence\t\ttrue\t\tmulti\\nline `code`\n\
+             X\tcombination\t5\tfalse\t1\tA2 B1..3 A\n"
+        );
+    }
+
+    #[test]
+    fn espanso() {
+        let (content, report) = export(&example(), ExportFormat::Espanso).unwrap();
+        assert!(report.is_empty());
+        assert!(content.contains("form: Hello [[arg1]] | <x>"), "{content}");
+        assert!(
+            content.contains("replace: '{{A}}{{A}}{{B}}{{A}}'"),
+            "{content}"
+        );
+
+        let file = tempfile::Builder::new().suffix(".yml").tempfile().unwrap();
+        std::fs::write(file.path(), &content).unwrap();
+        let mut imported = Combinations::default();
+        let report = import(
+            file.path(),
+            ImportFormat::Espanso,
+            &mut imported,
+            &Combinations::default(),
+        )
+        .unwrap();
+        assert_eq!(imported.list_all_commands(), vec!["A", "B", "X"]);
+        assert_eq!(
+            imported.get_expansion("A").unwrap(),
+            "Hello <1> | <x>",
+            "{report:?}"
+        );
+        assert_eq!(to_form("a <b> <12>"), Some(String::from("a <b> [[arg12]]")));
+        assert_eq!(to_form("a <b>"), None);
+    }
+
+    #[test]
+    fn espanso_secret() {
+        let combinations: Combinations = serde_json::from_str(
+            r#"{
+                "sequences": {"A": "user", "T": {"secret": {"env": "TOKEN"}}},
+                "combinations": {"L": {"sequence": "A T"}, "U": {"sequence": "A2"}}
+            }"#,
+        )
+        .unwrap();
+        let (content, report) = export(&combinations, ExportFormat::Espanso).unwrap();
+        assert_eq!(
+            report,
+            vec![
+                "\"L\" skipped, it types a secret",
+                "\"T\" skipped, it types a secret"
+            ]
+        );
+        assert!(!content.contains(":T"), "{content}");
+        assert!(!content.contains(":L"), "{content}");
+        assert!(!content.contains(crate::secret::MASK), "{content}");
+        assert!(content.contains("trigger: :U"), "{content}");
+    }
+}
@@ -0,0 +1,174 @@
+use crate::error::{ATResult, ErrAutoType, ErrType, Location};
+use clap::ValueEnum;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fmt::{self, Display},
+    path::Path,
+};
+
+/// Format of configuration file.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl Format {
+    /// Select format by extension of `path`.
+    ///
+    /// ```
+    /// # use shortcut_autotyper::Format;
+    /// # use std::path::Path;
+    /// assert_eq!(Format::from_path(Path::new("config.yml")).unwrap(), Format::Yaml);
+    /// assert!(Format::from_path(Path::new("config.ini")).is_err());
+    /// ```
+    pub fn from_path(path: &Path) -> ATResult<Format> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        Format::value_variants()
+            .iter()
+            .find(|format| format.extensions().contains(&extension))
+            .copied()
+            .ok_or_else(|| ErrType::UnknownFormat(path.display().to_string()).into())
+    }
+
+    /// Returns file extensions of the format, the first one is preferred.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Format::Json => &["json"],
+            Format::Toml => &["toml"],
+            Format::Yaml => &["yaml", "yml"],
+        }
+    }
+
+    /// Parse value from `content` of file on `path` in the format.
+    /// On failure returns [`ErrType::InvalidConfig`] with message
+    /// of the parser and position of the error, if it is known.
+    pub fn deserialize<T: DeserializeOwned>(&self, content: &str, path: &Path) -> ATResult<T> {
+        let (message, position) = match self {
+            Format::Json => match serde_json::from_str(content) {
+                Ok(value) => return Ok(value),
+                Err(e) => (
+                    e.to_string(),
+                    (e.line() > 0).then(|| (e.line(), e.column())),
+                ),
+            },
+            Format::Toml => match toml::from_str(content) {
+                Ok(value) => return Ok(value),
+                Err(e) => (
+                    String::from(e.message()),
+                    e.span().map(|span| line_column(content, span.start)),
+                ),
+            },
+            Format::Yaml => match serde_yaml::from_str(content) {
+                Ok(value) => return Ok(value),
+                Err(e) => (e.to_string(), e.location().map(|l| (l.line(), l.column()))),
+            },
+        };
+        let err = ErrAutoType::new_with_message(
+            ErrType::InvalidConfig(path.display().to_string()),
+            message,
+        );
+        Err(match position {
+            Some((line, column)) => {
+                err.with_location(Location::position(path, content, line, column))
+            }
+            None => err,
+        })
+    }
+
+    /// Serialize `value` to string in the format.
+    pub fn serialize<T: Serialize>(&self, value: &T) -> ATResult<String> {
+        let failed = |message: String| {
+            ErrAutoType::new_with_message(ErrType::CannotSerialize(self.to_string()), message)
+        };
+        match self {
+            Format::Json => serde_json::to_string_pretty(value)
+                .map(|content| content + "\n")
+                .map_err(|e| failed(e.to_string())),
+            Format::Toml => toml::to_string_pretty(value).map_err(|e| failed(e.to_string())),
+            Format::Yaml => serde_yaml::to_string(value).map_err(|e| failed(e.to_string())),
+        }
+    }
+}
+
+/// Returns line and column of byte `offset` in `content`, both counted from 1.
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.extensions()[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn from_path() {
+        for (path, format) in [
+            ("a/config.json", Format::Json),
+            ("config.toml", Format::Toml),
+            ("config.yaml", Format::Yaml),
+            ("~/.config.yml", Format::Yaml),
+        ] {
+            assert_eq!(Format::from_path(Path::new(path)), Ok(format));
+        }
+        assert_eq!(
+            Format::from_path(Path::new("config")),
+            ErrType::UnknownFormat(String::from("config")).into()
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let value = BTreeMap::from([("A", "multi\nline"), ("B", "text")]);
+        for format in Format::value_variants() {
+            let content = format.serialize(&value).unwrap();
+            assert_eq!(
+                format.deserialize::<BTreeMap<String, String>>(&content, Path::new("config")),
+                Ok(BTreeMap::from([
+                    (String::from("A"), String::from("multi\nline")),
+                    (String::from("B"), String::from("text")),
+                ])),
+                "{format}: {content}"
+            );
+        }
+    }
+
+    #[test]
+    fn deserialize_error() {
+        let path = Path::new("config");
+        for (format, content, position) in [
+            (Format::Json, "{\n  \"A\": \"a\",\n  \"B\" 1\n}", (3, 7)),
+            (Format::Toml, "A = \"a\"\nB = ", (2, 5)),
+            (Format::Yaml, "A: a\nB: [b\n", (2, 4)),
+        ] {
+            let err = format
+                .deserialize::<BTreeMap<String, String>>(content, path)
+                .unwrap_err();
+            assert_eq!(
+                err.get_type(),
+                &ErrType::InvalidConfig(String::from("config"))
+            );
+            assert_eq!(
+                err.get_location().and_then(Location::get_position),
+                Some(position),
+                "{format}: {err}"
+            );
+        }
+        assert_eq!(line_column("ab\ncd", 4), (2, 2));
+    }
+}
@@ -0,0 +1,393 @@
+use crate::{
+    combinations::Combinations,
+    command::{Command, RESERVED_NAMES},
+    error::{ATResult, ErrAutoType, ErrType},
+    espanso::{self, EspansoFile, EspansoVar},
+    format::Format,
+};
+use clap::ValueEnum;
+use std::{collections::HashSet, fs, path::Path};
+
+/// Name used for snippets without any usable character in their name.
+const FALLBACK_NAME: &str = "Snippet";
+
+/// Format of snippets imported from other text expanders.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// Espanso match file in YAML.
+    Espanso,
+    /// CSV with name and text in each row, optionally with header.
+    Csv,
+}
+
+impl ImportFormat {
+    /// Select format by extension of `path`.
+    pub fn from_path(path: &Path) -> ATResult<ImportFormat> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => Ok(ImportFormat::Csv),
+            Some("yml" | "yaml") => Ok(ImportFormat::Espanso),
+            _ => ErrType::UnknownFormat(path.display().to_string()).into(),
+        }
+    }
+}
+
+/// Snippet read from imported file before it is inserted to configuration.
+#[derive(Debug, PartialEq, Eq)]
+struct Snippet {
+    name: String,
+    text: String,
+}
+
+/// Import snippets from file on `path` in `format` to sequences
+/// of `combinations`. Names are sanitized to pass [`Command::valid_name()`]
+/// and made unique among entries of `combinations` and `base`, the merged
+/// configuration `combinations` are part of. Variables are translated
+/// to [`crate::Content`] placeholders. Returns report of everything, that
+/// was changed or could not be converted.
+pub fn import(
+    path: &Path,
+    format: ImportFormat,
+    combinations: &mut Combinations,
+    base: &Combinations,
+) -> ATResult<Vec<String>> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        ErrAutoType::new_with_message(
+            ErrType::InvalidImport(path.display().to_string()),
+            e.to_string(),
+        )
+    })?;
+    let mut report = Vec::new();
+    let snippets = match format {
+        ImportFormat::Espanso => {
+            let file: EspansoFile = Format::Yaml.deserialize(&content, path)?;
+            espanso(file, &mut report)
+        }
+        ImportFormat::Csv => csv(&content, path, &mut report)?,
+    };
+    let mut taken = combinations
+        .list_all_commands()
+        .into_iter()
+        .chain(base.list_all_commands())
+        .cloned()
+        .collect::<HashSet<_>>();
+    for snippet in snippets {
+        let name = unique_name(&snippet.name, &taken);
+        let prefix = |c: char| !c.is_alphabetic();
+        if name != snippet.name.trim_start_matches(prefix) {
+            report.push(format!("\"{}\" imported as \"{name}\"", snippet.name));
+        }
+        combinations.insert_sequence(&name, &snippet.text)?;
+        taken.insert(name);
+    }
+    Ok(report)
+}
+
+/// Convert espanso matches to snippets named by their first trigger.
+fn espanso(file: EspansoFile, report: &mut Vec<String>) -> Vec<Snippet> {
+    let mut snippets = Vec::new();
+    for m in file.matches {
+        let mut triggers = m.trigger.iter().chain(m.triggers.iter());
+        let Some(trigger) = triggers.next() else {
+            match &m.regex {
+                Some(regex) => report.push(format!(
+                    "Match with regex \"{regex}\" skipped, regex triggers are not supported"
+                )),
+                None => report.push(String::from("Match without trigger skipped")),
+            }
+            continue;
+        };
+        let others = triggers.map(|t| format!("\"{t}\"")).collect::<Vec<_>>();
+        if !others.is_empty() {
+            report.push(format!(
+                "\"{trigger}\": other triggers {} skipped",
+                others.join(", ")
+            ));
+        }
+        let text = if let Some(text) = &m.replace {
+            text.clone()
+        } else if let Some(text) = m.markdown.as_ref().or(m.html.as_ref()) {
+            report.push(format!(
+                "\"{trigger}\": formatted text imported as plain text"
+            ));
+            text.clone()
+        } else if let Some(form) = &m.form {
+            form.replace("[[", "{{").replace("]]", "}}")
+        } else {
+            match &m.image_path {
+                Some(_) => report.push(format!("\"{trigger}\" skipped, images are not supported")),
+                None => report.push(format!("\"{trigger}\" skipped, it has no text")),
+            }
+            continue;
+        };
+        let vars = m
+            .vars
+            .iter()
+            .chain(file.global_vars.iter())
+            .collect::<Vec<_>>();
+        snippets.push(Snippet {
+            name: trigger.clone(),
+            text: translate(trigger, &text, &vars, report),
+        });
+    }
+    snippets
+}
+
+/// Read snippets from CSV `content` with name and text columns. The first
+/// row is skipped if it is header `name,text`.
+fn csv(content: &str, path: &Path, report: &mut Vec<String>) -> ATResult<Vec<Snippet>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let mut snippets = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| {
+            ErrAutoType::new_with_message(
+                ErrType::InvalidImport(path.display().to_string()),
+                e.to_string(),
+            )
+        })?;
+        let row = i + 1;
+        match (record.get(0), record.get(1)) {
+            (Some(name), Some("text")) if row == 1 && name.eq_ignore_ascii_case("name") => {}
+            (Some(name), Some(text)) => {
+                if record.len() > 2 {
+                    report.push(format!("Row {row}: extra columns skipped"));
+                }
+                snippets.push(Snippet {
+                    name: String::from(name),
+                    text: translate(name, text, &[], report),
+                });
+            }
+            _ => report.push(format!("Row {row} skipped, it has no text")),
+        }
+    }
+    Ok(snippets)
+}
+
+/// Translate `{{name}}` variables in `text` of snippet `name` to `<n>`
+/// placeholders. Variables of type `echo` from `vars` are replaced
+/// by their value.
+fn translate(name: &str, text: &str, vars: &[&EspansoVar], report: &mut Vec<String>) -> String {
+    let mut text = text.to_string();
+    if text.contains(espanso::CURSOR) {
+        text = text.replace(espanso::CURSOR, "");
+        report.push(format!("\"{name}\": cursor position removed"));
+    }
+    if has_placeholder(&text) {
+        report.push(format!(
+            "\"{name}\": text like \"<1>\" is typed as argument of the sequence"
+        ));
+    }
+    let mut result = String::new();
+    let mut arguments: Vec<String> = Vec::new();
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}").map(|end| start + end) else {
+            break;
+        };
+        result += &rest[..start];
+        let variable = rest[start + 2..end].trim();
+        let base = variable.split('.').next().unwrap_or(variable);
+        let var = vars.iter().find(|var| var.name == base);
+        match var.and_then(|var| echo_value(var)) {
+            Some(value) => result += value,
+            None => {
+                let index = match arguments.iter().position(|arg| arg == variable) {
+                    Some(index) => index + 1,
+                    None => {
+                        arguments.push(String::from(variable));
+                        let kind = var.map_or("unknown", |var| var.kind.as_str());
+                        report.push(format!(
+                            "\"{name}\": variable \"{variable}\" ({kind}) became argument <{}>",
+                            arguments.len()
+                        ));
+                        arguments.len()
+                    }
+                };
+                result += &format!("<{index}>");
+            }
+        }
+        rest = &rest[end + 2..];
+    }
+    result + rest
+}
+
+/// Check if `text` contains anything like placeholder `<n>`.
+fn has_placeholder(text: &str) -> bool {
+    text.split('<').skip(1).any(|part| {
+        let digits = part.chars().take_while(char::is_ascii_digit).count();
+        digits > 0 && part[digits..].starts_with('>')
+    })
+}
+
+/// Returns constant value of espanso variable of type `echo`.
+fn echo_value(var: &EspansoVar) -> Option<&str> {
+    match var.kind.as_str() {
+        "echo" => var.params.get("echo")?.as_str(),
+        _ => None,
+    }
+}
+
+/// Keep only alphabetic characters of `name`.
+fn sanitize(name: &str) -> String {
+    name.chars().filter(|c| c.is_alphabetic()).collect()
+}
+
+/// Sanitize `name` and append alphabetic suffix if it is in `taken`
+/// or reserved for subcommand, so imported sequences can be typed
+/// without `--`.
+fn unique_name(name: &str, taken: &HashSet<String>) -> String {
+    let mut base = sanitize(name);
+    if base.is_empty() {
+        base = String::from(FALLBACK_NAME);
+    }
+    let mut name = base.clone();
+    let mut i = 0;
+    while taken.contains(&name)
+        || Command::valid_name(&name).is_err()
+        || RESERVED_NAMES.contains(&name.as_str())
+    {
+        name = format!("{base}{}", suffix(i));
+        i += 1;
+    }
+    name
+}
+
+/// Returns `i`-th alphabetic suffix `A`, `B`, ..., `Z`, `AA`, `AB`, ...
+fn suffix(mut i: usize) -> String {
+    let mut suffix = Vec::new();
+    loop {
+        suffix.push(char::from(b'A' + (i % 26) as u8));
+        if i < 26 {
+            break;
+        }
+        i = i / 26 - 1;
+    }
+    suffix.iter().rev().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn import_file(name: &str, content: &str, combinations: &mut Combinations) -> Vec<String> {
+        let file = tempfile::Builder::new().suffix(name).tempfile().unwrap();
+        fs::write(file.path(), content).unwrap();
+        import(
+            file.path(),
+            ImportFormat::from_path(file.path()).unwrap(),
+            combinations,
+            &Combinations::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn espanso() {
+        let mut combinations = Combinations::default();
+        combinations.insert_sequence("sig", "old").unwrap();
+        let report = import_file(
+            "espanso.yml",
+            r#"
+global_vars:
+  - name: company
+    type: echo
+    params:
+      echo: ACME
+matches:
+  - trigger: ":sig"
+    replace: "Regards,\n{{name}} from {{company}}$|$"
+    vars:
+      - name: name
+        type: form
+  - triggers: [":addr2", ":address"]
+    replace: "{{ date }} {{date}} {{clip}}"
+    vars:
+      - name: date
+        type: date
+  - regex: ":x(\\d+)"
+    replace: "x"
+  - trigger: ":logo"
+    image_path: "logo.png"
+  - trigger: ":hi"
+    form: "Hi [[who]]"
+"#,
+            &mut combinations,
+        );
+        assert_eq!(
+            report,
+            vec![
+                "\":sig\": cursor position removed",
+                "\":sig\": variable \"name\" (form) became argument <1>",
+                "\":addr2\": other triggers \":address\" skipped",
+                "\":addr2\": variable \"date\" (date) became argument <1>",
+                "\":addr2\": variable \"clip\" (unknown) became argument <2>",
+                "Match with regex \":x(\\d+)\" skipped, regex triggers are not supported",
+                "\":logo\" skipped, images are not supported",
+                "\":hi\": variable \"who\" (unknown) became argument <1>",
+                "\":sig\" imported as \"sigA\"",
+                "\":addr2\" imported as \"addr\"",
+            ]
+        );
+        let args = vec![String::from("_"), String::from("John")];
+        assert_eq!(
+            combinations.get_sequence("sigA", &args).unwrap(),
+            "Regards,\nJohn from ACME"
+        );
+        assert_eq!(
+            combinations.get_sequence("addr", &args).unwrap(),
+            "John John <2>"
+        );
+        assert_eq!(combinations.get_sequence("hi", &args).unwrap(), "Hi John");
+        assert_eq!(combinations.get_sequence("sig", &args).unwrap(), "old");
+    }
+
+    #[test]
+    fn csv() {
+        let mut combinations = Combinations::default();
+        let report = import_file(
+            "snippets.csv",
+            "name,text\nmail,john@example.com\n\"two words\",\"multi\nline, \"\"quoted\"\"\"\n123,<1>\nempty\n",
+            &mut combinations,
+        );
+        assert_eq!(
+            report,
+            vec![
+                "\"123\": text like \"<1>\" is typed as argument of the sequence",
+                "Row 5 skipped, it has no text",
+                "\"two words\" imported as \"twowords\"",
+                "\"123\" imported as \"Snippet\"",
+            ]
+        );
+        assert_eq!(
+            combinations.list_all_commands(),
+            vec!["Snippet", "mail", "twowords"]
+        );
+        assert_eq!(
+            combinations.get_sequence("twowords", &Vec::new()).unwrap(),
+            "multi\nline, \"quoted\""
+        );
+        assert!(import(
+            &PathBuf::from("missing.csv"),
+            ImportFormat::Csv,
+            &mut combinations,
+            &Combinations::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn unique_name() {
+        let taken = HashSet::from([String::from("a"), String::from("aA")]);
+        assert_eq!(super::unique_name(":b1", &taken), "b");
+        assert_eq!(super::unique_name(":a", &taken), "aB");
+        assert_eq!(super::unique_name("42", &taken), FALLBACK_NAME);
+        assert_eq!(super::unique_name(":stop", &taken), "stopA");
+        assert_eq!(suffix(0), "A");
+        assert_eq!(suffix(25), "Z");
+        assert_eq!(suffix(26), "AA");
+        assert_eq!(suffix(27 * 26), "AAA");
+    }
+}
@@ -1,11 +1,25 @@
 mod combinations;
 mod command;
+pub mod config;
 mod content;
+mod delay;
 pub mod error;
+mod espanso;
+pub mod export;
+mod format;
+pub mod import;
+pub mod lock;
+mod migration;
+mod schema;
+mod secret;
+mod segment;
 mod sequence;
 pub mod typer;
 
-pub use crate::combinations::Combinations;
-pub use crate::command::Command;
+pub use crate::combinations::{Combinations, Kind, ListOrder};
+pub use crate::command::{Command, RESERVED_NAMES};
 pub use crate::content::Content;
+pub use crate::delay::Delay;
+pub use crate::format::Format;
+pub use crate::segment::Segment;
 pub use crate::sequence::Sequences;
@@ -0,0 +1,216 @@
+use clap::ValueEnum;
+use std::{
+    env::{temp_dir, var, var_os},
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+const LOCK_NAME: &str = "shortcut-autotyper.lock";
+
+/// Behavior when another typing session is already running.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnBusy {
+    /// Wait until the running session ends.
+    Queue,
+    /// Stop the running session and start typing.
+    Replace,
+    /// Exit with an error.
+    Refuse,
+}
+
+/// Lock allowing only one typing session per user. The lock is a file
+/// locked by `flock` and containing PID of the process holding it. The lock
+/// is released on drop or when the process exits, so a lock of process
+/// that is not running anymore never blocks other sessions.
+#[derive(Debug)]
+pub struct SessionLock {
+    file: File,
+}
+
+impl SessionLock {
+    /// Returns path to lock in `$XDG_RUNTIME_DIR`. If it is not set, lock
+    /// in temporary directory with user name is used.
+    pub fn default_path() -> PathBuf {
+        match var_os("XDG_RUNTIME_DIR") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir).join(LOCK_NAME),
+            _ => temp_dir().join(format!(
+                "{}-{LOCK_NAME}",
+                var("USER").unwrap_or_else(|_| String::from("user"))
+            )),
+        }
+    }
+
+    /// Try to acquire lock on `path`. Returns [`None`] if lock is held
+    /// by another session. Taking over the lock is atomic, so only one
+    /// of concurrently started sessions acquires it.
+    pub fn try_acquire(path: &Path) -> io::Result<Option<SessionLock>> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        if !try_lock(&file, Lock::Exclusive)? {
+            return Ok(None);
+        }
+        file.set_len(0)?;
+        file.write_all(std::process::id().to_string().as_bytes())?;
+        Ok(Some(SessionLock { file }))
+    }
+
+    /// Returns PID of running process holding lock on `path`.
+    pub fn holder(path: &Path) -> io::Result<Option<u32>> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if try_lock(&file, Lock::Shared)? {
+            return Ok(None);
+        }
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        Ok(content.trim().parse::<u32>().ok())
+    }
+}
+
+impl Drop for SessionLock {
+    /// Clear PID in the lock file. The lock itself is released when
+    /// the file is closed. The file is not removed, because another
+    /// session could have it already opened.
+    fn drop(&mut self) {
+        let _ = self.file.set_len(0);
+    }
+}
+
+/// Mode of `flock` lock.
+#[derive(Clone, Copy)]
+enum Lock {
+    Shared,
+    Exclusive,
+}
+
+/// Try to lock `file` without blocking. Returns `false` if the file
+/// is locked by another open file description.
+#[cfg(unix)]
+fn try_lock(file: &File, lock: Lock) -> io::Result<bool> {
+    use std::os::fd::AsRawFd;
+    let operation = match lock {
+        Lock::Shared => libc::LOCK_SH,
+        Lock::Exclusive => libc::LOCK_EX,
+    };
+    // SAFETY: the file descriptor is valid while `file` is borrowed.
+    match unsafe { libc::flock(file.as_raw_fd(), operation | libc::LOCK_NB) } {
+        0 => Ok(true),
+        _ => match io::Error::last_os_error() {
+            e if e.raw_os_error() == Some(libc::EWOULDBLOCK) => Ok(false),
+            e => Err(e),
+        },
+    }
+}
+
+#[cfg(not(unix))]
+fn try_lock(_file: &File, _lock: Lock) -> io::Result<bool> {
+    Ok(true)
+}
+
+/// Send `SIGTERM` to process with `pid`.
+#[cfg(unix)]
+pub fn terminate(pid: u32) -> io::Result<()> {
+    let pid = libc::pid_t::try_from(pid).map_err(io::Error::other)?;
+    // SAFETY: sending signal has no memory safety requirements.
+    match unsafe { libc::kill(pid, libc::SIGTERM) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn terminate(_pid: u32) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        fs,
+        process::Command,
+        sync::{Arc, Barrier},
+        thread,
+    };
+    use tempfile::TempDir;
+
+    #[test]
+    fn try_acquire() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join(LOCK_NAME);
+        let lock = SessionLock::try_acquire(&path)?;
+        assert!(lock.is_some());
+        assert_eq!(SessionLock::holder(&path)?, Some(std::process::id()));
+        assert!(SessionLock::try_acquire(&path)?.is_none());
+        drop(lock);
+        assert_eq!(SessionLock::holder(&path)?, None);
+        let lock = SessionLock::try_acquire(&path)?;
+        assert!(lock.is_some());
+        assert_eq!(SessionLock::holder(&path)?, Some(std::process::id()));
+        Ok(())
+    }
+
+    #[test]
+    fn stale_lock() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join(LOCK_NAME);
+        let mut child = Command::new("true").spawn()?;
+        child.wait()?;
+        fs::write(&path, child.id().to_string())?;
+        assert_eq!(SessionLock::holder(&path)?, None);
+        let lock = SessionLock::try_acquire(&path)?;
+        assert!(lock.is_some());
+        assert_eq!(SessionLock::holder(&path)?, Some(std::process::id()));
+
+        fs::write(&path, "garbage")?;
+        assert_eq!(SessionLock::holder(&path)?, None);
+        drop(lock);
+        assert!(path.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn concurrent_acquire() -> io::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join(LOCK_NAME);
+        for _ in 0..20 {
+            fs::write(&path, "999999999")?;
+            let barrier = Arc::new(Barrier::new(8));
+            let acquirers = (0..8)
+                .map(|_| {
+                    let (path, barrier) = (path.clone(), barrier.clone());
+                    thread::spawn(move || {
+                        barrier.wait();
+                        let lock = SessionLock::try_acquire(&path).unwrap();
+                        let acquired = lock.is_some();
+                        barrier.wait();
+                        acquired
+                    })
+                })
+                .collect::<Vec<_>>();
+            let acquired = acquirers
+                .into_iter()
+                .map(|acquirer| acquirer.join().unwrap())
+                .filter(|acquired| *acquired)
+                .count();
+            assert_eq!(acquired, 1);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn terminate() -> io::Result<()> {
+        let mut child = Command::new("sleep").arg("10").spawn()?;
+        super::terminate(child.id())?;
+        assert!(!child.wait()?.success());
+        Ok(())
+    }
+}
@@ -1,26 +1,41 @@
-use clap::{Parser, ValueEnum};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use serde_json::{json, Value};
 use shortcut_autotyper::{
-    typer::{TypeText, Wtype, XDoTool},
-    Combinations,
+    config::{self, ConfigLocations},
+    error::{
+        ATResult, ErrAutoType, ErrType, Location, EXIT_CONFIG, EXIT_FAILURE, EXIT_IO, EXIT_TYPER,
+    },
+    export::{self, ExportFormat, ListFormat},
+    import::{self, ImportFormat},
+    lock::{terminate, OnBusy, SessionLock},
+    typer::{Backend, Session, Typer, TypingSession, X11},
+    Combinations, Command, Delay, Format, ListOrder, Segment,
 };
-use std::{env::var, error::Error, fs::File, process::exit};
+use std::{error::Error, fs, path::Path, process::exit, str::FromStr, time::Duration};
+use x11rb::errors::{ConnectError, ConnectionError, ReplyError, ReplyOrIdError};
 
-const CONFIG_NAME: &str = "/.shortcut_autotyper.json";
 const DEFAULT_DELAY: usize = 50;
-
-#[derive(ValueEnum, Clone, Debug)]
-pub enum Typer {
-    Xdotool,
-    Wtype,
-}
+const DEFAULT_CHUNK_SIZE: usize = 256;
 
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
+#[command(group(ArgGroup::new("listing").args(["list", "list_full"]).multiple(true)))]
 pub struct Args {
-    /// Set path to config file with sequences and combinations.
-    #[arg(short, long)]
-    #[arg(default_value_t = (||{ var("HOME").unwrap_or("~".into()) + CONFIG_NAME})())]
-    pub config: String,
+    /// Set path to config file with sequences and combinations. By default
+    /// `$XDG_CONFIG_HOME/shortcut-autotyper/config.{json,toml,yaml}` is merged over
+    /// system-wide configurations from `$XDG_CONFIG_DIRS`.
+    #[arg(short, long, global = true, env = "SHORTCUT_AUTOTYPER_CONFIG")]
+    pub config: Option<String>,
+
+    /// Apply sequences and combinations of given profile over the base
+    /// configuration.
+    #[arg(short, long, global = true, env = "SHORTCUT_AUTOTYPER_PROFILE")]
+    pub profile: Option<String>,
+
+    /// Format of printed errors. Exit code of the program also
+    /// depends on type of the error.
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text)]
+    error_format: ErrorFormat,
 
     /// List all avaible commands.
     #[arg(long)]
@@ -30,73 +45,526 @@ pub struct Args {
     #[arg(long)]
     list_full: bool,
 
-    /// Set delay between two key strokes. [default: 50]
+    /// Order of commands listed by `--list` and `--list-full`.
+    #[arg(long, value_enum, default_value_t = ListOrder::Name)]
+    order: ListOrder,
+
+    /// Print `--list` and `--list-full` in machine-readable format with name,
+    /// kind, delay, hidden flag, required arguments and expansion of entries.
+    #[arg(long, value_enum, requires = "listing")]
+    format: Option<ListFormat>,
+
+    /// Set delay between two key strokes. It can be a range like `30..90`,
+    /// then delay is randomly selected for every key stroke. [default: 50]
     #[arg(short, long)]
-    delay: Option<usize>,
+    delay: Option<Delay>,
+
+    /// Percentage of characters typed with a typo corrected by backspace.
+    #[arg(long, default_value_t = 0.0, env = "SHORTCUT_AUTOTYPER_TYPOS")]
+    typos: f64,
+
+    /// Wait given number of milliseconds before typing starts.
+    #[arg(short, long)]
+    wait: Option<usize>,
+
+    /// Wait until the active window changes from the window active when
+    /// the program started, at most given number of milliseconds. Requires X11 session. [default: 10000]
+    #[arg(long, num_args = 0..=1, default_missing_value = "10000")]
+    wait_focus: Option<u64>,
+
+    /// Maximal number of characters sent to typer at once.
+    #[arg(long, default_value_t = DEFAULT_CHUNK_SIZE)]
+    chunk_size: usize,
+
+    /// Binary to send text to terminal. It can be `auto`, `xdotool`, `wtype`,
+    /// `x11` or name of typer defined in config. With `auto` the first usable
+    /// typer from fallback chain is selected by session type.
+    #[arg(short, long, default_value = "auto")]
+    typer: String,
 
-    /// Binary to send text to terminal.
-    #[arg(short, long, default_value = "xdotool")]
-    typer: Typer,
+    /// Typers tried in given order when typer is `auto`.
+    #[arg(long, value_delimiter = ',', default_value = "wtype,xdotool,x11")]
+    #[arg(env = "SHORTCUT_AUTOTYPER_FALLBACK")]
+    fallback: Vec<Backend>,
+
+    /// What to do if another typing session is already running.
+    #[arg(long, value_enum, default_value_t = OnBusy::Queue)]
+    on_busy: OnBusy,
+
+    #[command(subcommand)]
+    action: Option<Action>,
 
     // HERE
     commands: Vec<String>,
 }
 
+#[derive(Debug, Subcommand)]
+enum Action {
+    /// Stop running typing session.
+    Stop,
+    /// Check configuration and print all errors in it. Exits with non-zero
+    /// code if configuration is not valid.
+    Check {
+        /// Configuration files checked separately instead of `--config`.
+        files: Vec<String>,
+    },
+    /// Convert configuration file to other format. Formats are selected
+    /// by file extensions. Includes are kept, included files are not converted.
+    Convert {
+        /// Configuration file to convert.
+        input: String,
+        /// Output file. If it is not set, converted configuration is printed.
+        output: Option<String>,
+        /// Format of output, required if output is not set.
+        #[arg(long, required_unless_present = "output")]
+        to: Option<Format>,
+    },
+    /// Print JSON Schema of configuration file.
+    Schema,
+    /// Export all sequences and combinations as cheat sheet or for other
+    /// text expanders.
+    Export {
+        /// Format of exported file.
+        #[arg(long)]
+        to: ExportFormat,
+        /// Output file. If it is not set, exported entries are printed.
+        output: Option<String>,
+    },
+    /// Import snippets from other text expanders as sequences to `--config`
+    /// or the user configuration. Names and variables, which had to be
+    /// changed, and snippets that could not be imported are reported.
+    Import {
+        /// Imported file.
+        file: String,
+        /// Format of imported file, selected by extension if not set.
+        #[arg(long)]
+        from: Option<ImportFormat>,
+    },
+    /// Upgrade configuration files from older versions and print what
+    /// changed. Backup of each changed file is kept with `.bak` extension.
+    Migrate {
+        /// Migrated files instead of `--config` or the user configuration.
+        files: Vec<String>,
+    },
+    /// Add sequence or combination to configuration file. The file
+    /// is `--config` or the user configuration, its backup is kept
+    /// with `.bak` extension.
+    Add {
+        /// Name of the new sequence or combination.
+        key: String,
+        /// Text of sequence, or sequences and combinations like "A2 B"
+        /// with `--combination`.
+        value: String,
+        /// Add combination instead of sequence.
+        #[arg(long)]
+        combination: bool,
+    },
+    /// Remove sequence or combination from configuration file. It cannot
+    /// be removed while other combinations reference it.
+    Remove {
+        /// Name of removed sequence or combination.
+        key: String,
+    },
+    /// Rename sequence or combination in configuration file and rewrite
+    /// all combinations referencing it.
+    Rename {
+        /// Current name.
+        key: String,
+        /// New name.
+        new_key: String,
+    },
+}
+
 impl Args {
-    // TODO new is not right name
-    pub fn run() -> Result<Self, Box<dyn Error>> {
-        let args = Self::parse();
-        if args.list {
-            args.get_combinations()?
-                .list_all_commands()
-                .iter()
-                .filter(|command| !command.starts_with("_"))
-                .for_each(|command| {
-                    println!("{command}");
-                });
-            exit(0);
+    /// Run selected subcommand or listing and exit. Returns if commands
+    /// should be typed.
+    pub fn run(&self) -> Result<(), Box<dyn Error>> {
+        match &self.action {
+            Some(Action::Stop) => {
+                match SessionLock::holder(&SessionLock::default_path())? {
+                    Some(pid) => terminate(pid)?,
+                    None => Err(ErrAutoType::from(ErrType::NoSessionRunning))?,
+                }
+                exit(0);
+            }
+            Some(Action::Check { files }) => {
+                let locations = ConfigLocations::from_env();
+                let configs = match files.is_empty() {
+                    true => vec![locations.find(self.config.as_deref())?],
+                    false => files
+                        .iter()
+                        .map(|file| vec![locations.expand(file)])
+                        .collect(),
+                };
+                let mut valid = true;
+                for paths in configs {
+                    let file = (!files.is_empty()).then(|| paths[0].as_path());
+                    let mut errors = Vec::new();
+                    match config::load_unchecked(&paths) {
+                        Ok(combinations) => {
+                            let base = combinations.get_errors().err().unwrap_or_default();
+                            errors.push((None, base));
+                            for profile in combinations.list_profiles() {
+                                let profile_errors = combinations
+                                    .get_profile_errors(profile)
+                                    .err()
+                                    .unwrap_or_default();
+                                errors.push((Some(profile.clone()), profile_errors));
+                            }
+                        }
+                        Err(e) => errors.push((None, vec![e])),
+                    };
+                    for (profile, errors) in errors {
+                        errors
+                            .iter()
+                            .for_each(|e| self.error_format.print(e, file, profile.as_deref()));
+                        valid &= errors.is_empty();
+                    }
+                }
+                exit(if valid { 0 } else { EXIT_CONFIG });
+            }
+            Some(Action::Convert { input, output, to }) => {
+                let locations = ConfigLocations::from_env();
+                let combinations = Combinations::read(&locations.expand(input))?;
+                let output = output.as_deref().map(|output| locations.expand(output));
+                let format = match (to, &output) {
+                    (Some(format), _) => *format,
+                    (None, Some(output)) => Format::from_path(output)?,
+                    (None, None) => unreachable!("clap requires format without output"),
+                };
+                let content = combinations.to_format(format)?;
+                match output {
+                    Some(output) => fs::write(output, content)?,
+                    None => print!("{content}"),
+                }
+                exit(0);
+            }
+            Some(Action::Add {
+                key,
+                value,
+                combination,
+            }) => {
+                let base = self.get_merged();
+                base.check_unused(key)?;
+                self.edit_config(|combinations| match combination {
+                    true => combinations.insert_with_base(key, value, &base),
+                    false => combinations.insert_sequence(key, value),
+                })?;
+                exit(0);
+            }
+            Some(Action::Remove { key }) => {
+                let mut overridden = Vec::new();
+                self.edit_config(|combinations| {
+                    overridden = combinations.remove(key)?;
+                    Ok(())
+                })?;
+                for profile in overridden {
+                    println!("Removed \"{key}\" also from profile \"{profile}\"");
+                }
+                exit(0);
+            }
+            Some(Action::Rename { key, new_key }) => {
+                self.get_merged().check_unused(new_key)?;
+                self.edit_config(|combinations| combinations.rename(key, new_key))?;
+                exit(0);
+            }
+            Some(Action::Migrate { files }) => {
+                let locations = ConfigLocations::from_env();
+                let paths = match files.is_empty() {
+                    true => vec![locations.editable(self.config.as_deref())?],
+                    false => files.iter().map(|file| locations.expand(file)).collect(),
+                };
+                for path in paths {
+                    let changes = config::migrate(&path)?;
+                    if changes.is_empty() {
+                        println!("{}: up to date", path.display());
+                    }
+                    for change in changes {
+                        println!("{}: {change}", path.display());
+                    }
+                }
+                exit(0);
+            }
+            Some(Action::Export { to, output }) => {
+                let (content, report) = export::export(&self.get_combinations()?, *to)?;
+                report.iter().for_each(|line| eprintln!("{line}"));
+                match output {
+                    Some(output) => fs::write(ConfigLocations::from_env().expand(output), content)?,
+                    None => print!("{content}"),
+                }
+                exit(0);
+            }
+            Some(Action::Import { file, from }) => {
+                let path = ConfigLocations::from_env().expand(file);
+                let format = match from {
+                    Some(format) => *format,
+                    None => ImportFormat::from_path(&path)?,
+                };
+                let base = self.get_merged();
+                let (mut report, mut imported) = (Vec::new(), 0);
+                self.edit_config(|combinations| {
+                    let before = combinations.list_all_commands().len();
+                    report = import::import(&path, format, combinations, &base)?;
+                    imported = combinations.list_all_commands().len() - before;
+                    Ok(())
+                })?;
+                report.iter().for_each(|line| println!("{line}"));
+                println!("Imported {imported} sequences");
+                exit(0);
+            }
+            Some(Action::Schema) => {
+                println!("{}", serde_json::to_string_pretty(&Combinations::schema())?);
+                exit(0);
+            }
+            None => {}
         }
-        if args.list_full {
-            let combinations = args.get_combinations()?;
-            combinations
-                .list_all_commands()
-                .iter()
-                .filter(|command| !command.starts_with("_"))
-                .for_each(|command| {
-                    println!(
-                        "{command}: {}",
-                        combinations
-                            .get_sequence(command, &Vec::new())
-                            .unwrap()
-                            .replace("\n", "\\n")
-                    );
-                });
+        if self.list || self.list_full {
+            self.list()?;
             exit(0);
         }
-        Ok(args)
+        Ok(())
+    }
+
+    /// Print available commands in selected order, with generated
+    /// sequences if `--list-full` is set.
+    fn list(&self) -> ATResult<()> {
+        let combinations = self.get_combinations()?;
+        if let Some(format) = self.format {
+            print!("{}", export::list(&combinations, self.order, format)?);
+            return Ok(());
+        }
+        let mut section = None;
+        for (kind, command) in combinations.list_commands(self.order) {
+            if combinations.is_hidden(command) {
+                continue;
+            }
+            if self.order == ListOrder::Kind && section != Some(kind) {
+                if section.is_some() {
+                    println!();
+                }
+                println!("{}:", kind.title());
+                section = Some(kind);
+            }
+            match self.list_full {
+                true => println!(
+                    "{command}: {}",
+                    combinations
+                        .get_sequence(command, &Vec::new())?
+                        .replace("\n", "\\n")
+                ),
+                false => println!("{command}"),
+            }
+        }
+        Ok(())
     }
 
-    fn get_combinations(&self) -> Result<Combinations, Box<dyn Error>> {
-        Ok(serde_json::from_reader(File::open(&self.config)?)?)
+    fn get_combinations(&self) -> ATResult<Combinations> {
+        config::load(
+            &ConfigLocations::from_env().find(self.config.as_deref())?,
+            self.profile.as_deref(),
+        )
+    }
+
+    /// Returns all configuration layers with their includes merged without
+    /// validation. Layers which cannot be loaded are left out, so editing
+    /// of the configuration is possible even if it is broken.
+    fn get_merged(&self) -> Combinations {
+        let paths = ConfigLocations::from_env()
+            .find(self.config.as_deref())
+            .unwrap_or_default();
+        paths
+            .into_iter()
+            .filter(|path| path.exists())
+            .filter_map(|path| config::load_unchecked(&[path]).ok())
+            .fold(Combinations::default(), |mut merged, layer| {
+                merged.merge(layer);
+                merged
+            })
+    }
+
+    /// Apply `edit` to the editable configuration file and save it.
+    fn edit_config<F>(&self, edit: F) -> ATResult<()>
+    where
+        F: FnOnce(&mut Combinations) -> ATResult<()>,
+    {
+        let path = ConfigLocations::from_env().editable(self.config.as_deref())?;
+        let mut combinations = match path.exists() {
+            true => Combinations::read(&path)?,
+            false => Combinations::default(),
+        };
+        edit(&mut combinations)?;
+        config::save(&path, &combinations)
     }
 
     fn type_text(&self) -> Result<(), Box<dyn Error>> {
+        let Some(key) = self.commands.first() else {
+            Err(ErrAutoType::from(ErrType::ArgumentMissing(String::from(
+                "COMMANDS",
+            ))))?
+        };
+        // Window is taken before anything slow, so switching windows
+        // while configuration is loaded or lock is awaited is noticed.
+        let launcher = match self.wait_focus {
+            Some(_) => {
+                let x11 = X11::connect(None)?;
+                let window = x11.active_window()?;
+                Some((x11, window))
+            }
+            None => None,
+        };
         let c = self.get_combinations()?;
-        let sequence = c.get_sequence(&self.commands[0], &self.commands)?;
-        let delay = self
-            .delay
-            .or_else(|| c.get_delay(&self.commands[0]))
-            .unwrap_or(DEFAULT_DELAY);
-        match &self.typer {
-            Typer::Xdotool => XDoTool::type_text(sequence, delay)?,
-            Typer::Wtype => Wtype::type_text(sequence, delay)?,
+        let typer = self.get_typer(&c)?;
+        if typer.exposes_text() && c.uses_secret(key) {
+            Err(ErrAutoType::new_with_message(
+                ErrType::SecretUnavailable(key.clone()),
+                format!(
+                    "typer \"{}\" passes text in arguments visible to other processes, set \"stdin\" instead",
+                    self.typer
+                ),
+            ))?
         }
+        let segments = c
+            .get_segments(key, &self.commands)?
+            .iter()
+            .map(|segment| {
+                let delay = self.delay.as_ref().or(segment.get_delay()).cloned();
+                Segment::new(segment.get_text(), delay)
+            })
+            .collect();
+        let segments = c.apply_char_delays(segments);
+        let mut session = TypingSession::new(typer, segments, self.chunk_size);
+        session.handle_signals()?;
+        session.acquire_lock(&SessionLock::default_path(), self.on_busy)?;
+        if let (Some(timeout), Some((x11, window))) = (self.wait_focus, launcher) {
+            x11.wait_for_focus_change(window, Duration::from_millis(timeout))?;
+        }
+        let wait = self
+            .wait
+            .or_else(|| c.get_start_delay(&Command::from_str(key).ok()?))
+            .unwrap_or_default();
+        session.wait(Duration::from_millis(wait as u64))?;
+        session.type_segments(&Delay::Fixed(DEFAULT_DELAY), self.typos / 100.0)?;
         Ok(())
     }
+
+    fn get_typer(&self, combinations: &Combinations) -> ATResult<Typer> {
+        if self.typer == "auto" {
+            return Session::from_env()
+                .select(&self.fallback)
+                .map(Typer::Backend);
+        }
+        match combinations.get_typer(&self.typer) {
+            Some(template) => Ok(Typer::Custom(template.clone())),
+            None => match Backend::from_str(&self.typer, false) {
+                Ok(backend) => Ok(Typer::Backend(backend)),
+                Err(_) => ErrType::UnknownTyper(self.typer.clone()).into(),
+            },
+        }
+    }
+}
+
+/// Format of errors printed to standard error.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ErrorFormat {
+    /// Description of the error with position in configuration.
+    Text,
+    /// JSON object on single line with type, exit code, key and message.
+    Json,
+}
+
+impl ErrorFormat {
+    /// Print `error` found in configuration `file` with `profile` applied.
+    /// In text format, the file is printed only if location of the error
+    /// does not contain it.
+    fn print(&self, error: &ErrAutoType, file: Option<&Path>, profile: Option<&str>) {
+        match self {
+            ErrorFormat::Text => {
+                let mut label = String::new();
+                if let (Some(file), None) =
+                    (file, error.get_location().and_then(Location::get_file))
+                {
+                    label += &format!("{}: ", file.display());
+                }
+                if let Some(profile) = profile {
+                    label += &format!("profile \"{profile}\": ");
+                }
+                eprintln!("{label}{error}");
+            }
+            ErrorFormat::Json => {
+                let mut json = error.to_json();
+                if let (Some(file), true) = (file, json["file"].is_null()) {
+                    json["file"] = Value::from(file.display().to_string());
+                }
+                if let Some(profile) = profile {
+                    json["profile"] = Value::from(profile);
+                }
+                eprintln!("{json}");
+            }
+        }
+    }
+
+    /// Print any `error` of the program.
+    fn print_any(&self, error: &(dyn Error + 'static)) {
+        match (self, error.downcast_ref::<ErrAutoType>()) {
+            (_, Some(error)) => self.print(error, None, None),
+            (ErrorFormat::Text, None) => eprintln!("Error: {error}"),
+            (ErrorFormat::Json, None) => {
+                let (name, code) = classify(error);
+                let json = json!({
+                    "type": name,
+                    "code": code,
+                    "key": null,
+                    "message": error.to_string(),
+                    "file": null,
+                    "line": null,
+                    "column": null,
+                });
+                eprintln!("{json}");
+            }
+        }
+    }
+}
+
+/// Returns type name and exit code of `error`. Errors of X11
+/// connection are typer failures.
+fn classify(error: &(dyn Error + 'static)) -> (&'static str, i32) {
+    if let Some(error) = error.downcast_ref::<ErrAutoType>() {
+        (error.get_type().name(), error.get_type().exit_code())
+    } else if error.is::<std::io::Error>() || error.is::<serde_json::Error>() {
+        ("io", EXIT_IO)
+    } else if error.is::<ConnectError>()
+        || error.is::<ConnectionError>()
+        || error.is::<ReplyError>()
+        || error.is::<ReplyOrIdError>()
+    {
+        ("typer_failed", EXIT_TYPER)
+    } else {
+        ("failure", EXIT_FAILURE)
+    }
 }
 
 fn main() {
-    if let Err(e) = Args::run().and_then(|a| a.type_text()) {
-        eprintln!("{}", e)
+    let args = Args::parse();
+    if let Err(e) = args.run().and_then(|()| args.type_text()) {
+        args.error_format.print_any(e.as_ref());
+        exit(classify(e.as_ref()).1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+    use shortcut_autotyper::RESERVED_NAMES;
+
+    #[test]
+    fn reserved_names() {
+        let mut subcommands = Args::command()
+            .get_subcommands()
+            .map(|subcommand| String::from(subcommand.get_name()))
+            .chain([String::from("help")])
+            .collect::<Vec<_>>();
+        subcommands.sort();
+        assert_eq!(subcommands, RESERVED_NAMES);
     }
 }
@@ -0,0 +1,194 @@
+use crate::error::{ATResult, ErrType};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Version of configuration written by this version of the program.
+pub const CURRENT_VERSION: u32 = 2;
+
+/// Version of configuration files without `version` field.
+const LEGACY_VERSION: u32 = 1;
+
+/// Top-level keys of current configuration.
+const KNOWN_KEYS: &[&str] = &[
+    "$schema",
+    "version",
+    "include",
+    "combinations",
+    "sequences",
+    "typers",
+    "char_delays",
+    "profiles",
+];
+
+/// Version of configuration file format. Default is [`CURRENT_VERSION`].
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Version(u32);
+
+impl Default for Version {
+    fn default() -> Self {
+        Version(CURRENT_VERSION)
+    }
+}
+
+/// Result of configuration migration.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Migration {
+    from: u32,
+    changes: Vec<String>,
+}
+
+impl Migration {
+    /// Migration of configuration, which is already current.
+    fn unchanged() -> Migration {
+        Migration {
+            from: CURRENT_VERSION,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Returns `true` if shape of configuration was changed.
+    pub fn is_reshaped(&self) -> bool {
+        !self.changes.is_empty()
+    }
+
+    /// Returns descriptions of all changes including version upgrade.
+    pub fn describe(self) -> Vec<String> {
+        let mut changes = self.changes;
+        if self.from < CURRENT_VERSION {
+            changes.push(format!(
+                "Version upgraded from {} to {CURRENT_VERSION}",
+                self.from
+            ));
+        }
+        changes
+    }
+}
+
+/// Upgrade configuration `value` of any older version to [`CURRENT_VERSION`].
+/// Values with unexpected shape are kept unchanged, so they are reported
+/// by deserialization. Returns [`ErrType::UnsupportedVersion`] if version
+/// of configuration is newer than [`CURRENT_VERSION`].
+pub fn migrate(value: &mut Value) -> ATResult<Migration> {
+    let Value::Object(config) = value else {
+        return Ok(Migration::unchanged());
+    };
+    let from = match config.get("version") {
+        None => LEGACY_VERSION,
+        Some(version) => match version.as_u64().and_then(|v| u32::try_from(v).ok()) {
+            Some(version) => version,
+            None => return Ok(Migration::unchanged()),
+        },
+    };
+    if from > CURRENT_VERSION {
+        return ErrType::UnsupportedVersion(from, CURRENT_VERSION).into();
+    }
+    let mut changes = Vec::new();
+    if from < 2 {
+        changes.extend(split_top_level(config));
+        changes.extend(expand_combinations(config));
+    }
+    config.insert(String::from("version"), Value::from(CURRENT_VERSION));
+    Ok(Migration { from, changes })
+}
+
+/// Move entries of configuration consisting only of top-level names
+/// to sequences and combinations. Objects with `sequence` field
+/// are combinations, everything else are sequences.
+fn split_top_level(config: &mut Map<String, Value>) -> Vec<String> {
+    if config.is_empty() || config.keys().any(|key| KNOWN_KEYS.contains(&key.as_str())) {
+        return Vec::new();
+    }
+    let mut sequences = Map::new();
+    let mut combinations = Map::new();
+    let mut changes = Vec::new();
+    for (key, value) in std::mem::take(config) {
+        let kind = match value.get("sequence") {
+            Some(_) => {
+                combinations.insert(key.clone(), value);
+                "combinations"
+            }
+            None => {
+                sequences.insert(key.clone(), value);
+                "sequences"
+            }
+        };
+        changes.push(format!("Top-level \"{key}\" moved to {kind}"));
+    }
+    config.insert(String::from("combinations"), Value::Object(combinations));
+    config.insert(String::from("sequences"), Value::Object(sequences));
+    changes
+}
+
+/// Convert combinations written as plain strings to objects.
+fn expand_combinations(config: &mut Map<String, Value>) -> Vec<String> {
+    let Some(Value::Object(combinations)) = config.get_mut("combinations") else {
+        return Vec::new();
+    };
+    let mut changes = Vec::new();
+    for (key, value) in combinations.iter_mut() {
+        if let Value::String(sequence) = value {
+            let mut combination = Map::new();
+            combination.insert(String::from("sequence"), Value::String(sequence.clone()));
+            *value = Value::Object(combination);
+            changes.push(format!("Combination \"{key}\" converted to object"));
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn migrate() {
+        let mut value = json!({"A": "a", "B": {"text": "b", "delay": 5}, "X": {"sequence": "A B"}});
+        assert_eq!(
+            super::migrate(&mut value).unwrap().describe(),
+            vec![
+                "Top-level \"A\" moved to sequences",
+                "Top-level \"B\" moved to sequences",
+                "Top-level \"X\" moved to combinations",
+                "Version upgraded from 1 to 2",
+            ]
+        );
+        assert_eq!(
+            value,
+            json!({
+                "version": 2,
+                "sequences": {"A": "a", "B": {"text": "b", "delay": 5}},
+                "combinations": {"X": {"sequence": "A B"}}
+            })
+        );
+        assert_eq!(
+            super::migrate(&mut value).unwrap().describe(),
+            Vec::<String>::new()
+        );
+
+        let mut value =
+            json!({"sequences": {"A": "a"}, "combinations": {"X": "A2", "Y": {"sequence": "X"}}});
+        assert_eq!(
+            super::migrate(&mut value).unwrap().describe(),
+            vec![
+                "Combination \"X\" converted to object",
+                "Version upgraded from 1 to 2",
+            ]
+        );
+        assert_eq!(value["combinations"]["X"], json!({"sequence": "A2"}));
+
+        let mut value = json!({"version": 3});
+        assert_eq!(
+            super::migrate(&mut value),
+            ErrType::UnsupportedVersion(3, CURRENT_VERSION).into()
+        );
+        let mut value = json!({"version": 1, "sequences": {"A": "a"}});
+        let migration = super::migrate(&mut value).unwrap();
+        assert!(!migration.is_reshaped());
+        assert_eq!(migration.describe(), vec!["Version upgraded from 1 to 2"]);
+        let mut value = json!({"version": "2"});
+        assert_eq!(super::migrate(&mut value).unwrap(), Migration::unchanged());
+    }
+}
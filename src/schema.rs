@@ -0,0 +1,113 @@
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, ObjectValidation, Schema, SchemaObject, StringValidation},
+    JsonSchema,
+};
+
+/// Pattern of names of sequences and combinations, see [`crate::Command::valid_name()`].
+pub const NAME_PATTERN: &str = r"^\p{Alphabetic}+$";
+
+/// Pattern of combination. It is a list of names separated by white spaces,
+/// each optionally followed by number of repetitions or range like `2..5`.
+pub const COMBINATION_PATTERN: &str = r"^\s*(\p{Alphabetic}+([0-9]+(\.\.[0-9]+)?)?(\s+|$))*$";
+
+/// Schema of map from names of sequences or combinations to `T`.
+pub fn names_map<T: JsonSchema>(gen: &mut SchemaGenerator) -> Schema {
+    map_schema::<T>(
+        gen,
+        StringValidation {
+            pattern: Some(String::from(NAME_PATTERN)),
+            ..Default::default()
+        },
+    )
+}
+
+/// Schema of map from single characters to `T`.
+pub fn chars_map<T: JsonSchema>(gen: &mut SchemaGenerator) -> Schema {
+    map_schema::<T>(
+        gen,
+        StringValidation {
+            min_length: Some(1),
+            max_length: Some(1),
+            ..Default::default()
+        },
+    )
+}
+
+/// Schema of map with keys validated by `keys` and values of type `T`.
+fn map_schema<T: JsonSchema>(gen: &mut SchemaGenerator, keys: StringValidation) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::Object.into()),
+        object: Some(Box::new(ObjectValidation {
+            additional_properties: Some(Box::new(gen.subschema_for::<T>())),
+            property_names: Some(Box::new(
+                SchemaObject {
+                    instance_type: Some(InstanceType::String.into()),
+                    string: Some(Box::new(keys)),
+                    ..Default::default()
+                }
+                .into(),
+            )),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{command::Command, Combinations};
+    use regex::Regex;
+    use std::str::FromStr;
+
+    #[test]
+    fn name_pattern() {
+        let pattern = Regex::new(NAME_PATTERN).unwrap();
+        for name in ["A", "abc", "Žluťoučký", "", "A1", "A B", "A~", "_A"] {
+            assert_eq!(
+                pattern.is_match(name),
+                Command::valid_name(name).is_ok(),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn combination_pattern() {
+        let pattern = Regex::new(COMBINATION_PATTERN).unwrap();
+        for combination in [
+            "A", "A2 B3..5", " A\tB10 ", "", "A B", "A~", "2A", "A2..", "A2..x", "A..3", "A2B",
+        ] {
+            let valid = combination
+                .split_whitespace()
+                .all(|command| Command::from_str(command).is_ok());
+            assert_eq!(pattern.is_match(combination), valid, "{combination}");
+        }
+    }
+
+    #[test]
+    fn schema() {
+        let schema = serde_json::to_value(Combinations::schema()).unwrap();
+        let properties = &schema["properties"];
+        assert_eq!(
+            properties["sequences"]["allOf"][0]["$ref"],
+            "#/definitions/Sequences"
+        );
+        assert_eq!(
+            schema["definitions"]["Sequences"]["propertyNames"]["pattern"],
+            NAME_PATTERN
+        );
+        assert_eq!(
+            properties["combinations"]["propertyNames"]["pattern"],
+            NAME_PATTERN
+        );
+        assert_eq!(
+            schema["definitions"]["Combination"]["properties"]["sequence"]["pattern"],
+            COMBINATION_PATTERN
+        );
+        assert_eq!(properties["char_delays"]["propertyNames"]["maxLength"], 1);
+        assert!(properties.get("sources").is_none());
+    }
+}
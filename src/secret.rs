@@ -0,0 +1,149 @@
+use crate::{
+    config::ConfigLocations,
+    error::{ATResult, ErrAutoType, ErrType},
+};
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+#[cfg(test)]
+use std::{cell::RefCell, collections::HashMap};
+use std::{
+    env,
+    fmt::{self, Display},
+    process::Command,
+};
+
+/// Text shown instead of secret sequences in listings and errors.
+pub const MASK: &str = "********";
+
+#[cfg(test)]
+thread_local! {
+    /// Environment variables of the current test. Process environment
+    /// is shared by tests running in parallel, so it is never changed.
+    static TEST_VARS: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+}
+
+/// Set environment variable `name` seen by secrets of the current test
+/// thread, or remove it if `value` is [`None`].
+#[cfg(test)]
+pub(crate) fn set_test_var(name: &str, value: Option<&str>) {
+    TEST_VARS.with_borrow_mut(|vars| match value {
+        Some(value) => vars.insert(String::from(name), String::from(value)),
+        None => vars.remove(name),
+    });
+}
+
+/// Returns value of environment variable `name`.
+fn var(name: &str) -> Result<String, env::VarError> {
+    #[cfg(test)]
+    if let Some(value) = TEST_VARS.with_borrow(|vars| vars.get(name).cloned()) {
+        return Ok(value);
+    }
+    env::var(name)
+}
+
+/// Provider of secret text. Only the reference to the secret is stored
+/// in configuration, the text is fetched when the sequence is typed.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Secret {
+    /// Name of password in `pass` password store. Only its first line is used.
+    Pass(String),
+    /// Attributes of secret looked up by `secret-tool` in the keyring.
+    #[serde(rename = "secret_tool")]
+    Keyring(IndexMap<String, String>),
+    /// Name of environment variable with the secret.
+    Env(String),
+    /// Path to file encrypted by GnuPG.
+    File(String),
+}
+
+impl Secret {
+    /// Fetch text of the secret of sequence `key` from its provider. Returns
+    /// [`ErrType::SecretUnavailable`] if the provider fails.
+    pub fn reveal(&self, key: &str) -> ATResult<String> {
+        let failed = |message: String| {
+            ErrAutoType::new_with_message(
+                ErrType::SecretUnavailable(String::from(key)),
+                format!("{self}: {message}"),
+            )
+        };
+        let text = match self {
+            Secret::Pass(name) => {
+                let text = output(Command::new("pass").args(["show", name])).map_err(failed)?;
+                return Ok(String::from(text.lines().next().unwrap_or_default()));
+            }
+            Secret::Keyring(attributes) => output(
+                Command::new("secret-tool")
+                    .arg("lookup")
+                    .args(attributes.iter().flat_map(|(k, v)| [k, v])),
+            ),
+            Secret::Env(name) => var(name).map_err(|e| e.to_string()),
+            Secret::File(path) => output(
+                Command::new("gpg")
+                    .args(["--quiet", "--batch", "--decrypt"])
+                    .arg(ConfigLocations::from_env().expand(path)),
+            ),
+        }
+        .map_err(failed)?;
+        Ok(text.strip_suffix('\n').map(String::from).unwrap_or(text))
+    }
+}
+
+impl Display for Secret {
+    /// Describes where the secret is stored, never the secret itself.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Secret::Pass(name) => write!(f, "pass \"{name}\""),
+            Secret::Keyring(attributes) => {
+                write!(f, "secret-tool")?;
+                attributes
+                    .iter()
+                    .try_for_each(|(k, v)| write!(f, " {k}=\"{v}\""))
+            }
+            Secret::Env(name) => write!(f, "environment variable \"{name}\""),
+            Secret::File(path) => write!(f, "file \"{path}\""),
+        }
+    }
+}
+
+/// Run `command` and return its standard output. Standard error
+/// of the command is returned if it fails.
+fn output(command: &mut Command) -> Result<String, String> {
+    let output = command.output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(match stderr.trim() {
+            "" => output.status.to_string(),
+            stderr => String::from(stderr),
+        });
+    }
+    String::from_utf8(output.stdout).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reveal() {
+        let name = "SHORTCUT_AUTOTYPER_TEST_SECRET";
+        set_test_var(name, Some("s3cr3t\n"));
+        let secret = Secret::Env(String::from(name));
+        assert_eq!(secret.reveal("Token").unwrap(), "s3cr3t");
+        set_test_var(name, None);
+        let err = secret.reveal("Token").unwrap_err();
+        assert_eq!(
+            err,
+            ErrType::SecretUnavailable(String::from("Token")).into()
+        );
+        assert!(!err.to_string().contains("s3cr3t"));
+
+        let secret: Secret =
+            serde_json::from_str(r#"{"secret_tool": {"service": "git", "user": "me"}}"#).unwrap();
+        assert_eq!(
+            secret.to_string(),
+            "secret-tool service=\"git\" user=\"me\""
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use crate::delay::Delay;
+use indexmap::IndexMap;
+
+/// Part of generated text typed with the same delay between key strokes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    text: String,
+    delay: Option<Delay>,
+}
+
+impl Segment {
+    /// Create new [`Segment`] with `text` typed with `delay`. If `delay`
+    /// is [`None`] default delay is used.
+    pub fn new(text: &str, delay: Option<Delay>) -> Segment {
+        Segment {
+            text: String::from(text),
+            delay,
+        }
+    }
+
+    /// Return reference to text of the segment.
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    /// Return delay between key strokes of the segment.
+    pub fn get_delay(&self) -> Option<&Delay> {
+        self.delay.as_ref()
+    }
+
+    /// Split segment to characters with delay given by `char_delays`
+    /// and parts between them typed with delay of the segment.
+    ///
+    /// ```
+    /// # use shortcut_autotyper::Segment;
+    /// # use indexmap::IndexMap;
+    /// let char_delays = IndexMap::from([('\n', 250.into())]);
+    /// assert_eq!(
+    ///     Segment::new("a\nb", None).split(&char_delays),
+    ///     vec![
+    ///         Segment::new("a", None),
+    ///         Segment::new("\n", Some(250.into())),
+    ///         Segment::new("b", None),
+    ///     ]
+    /// );
+    /// ```
+    pub fn split(&self, char_delays: &IndexMap<char, Delay>) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        let mut last = String::new();
+        for c in self.text.chars() {
+            match char_delays.get(&c) {
+                Some(delay) => {
+                    segments.push(Segment::new(&last, self.delay.clone()));
+                    segments.push(Segment::new(&c.to_string(), Some(delay.clone())));
+                    last.clear();
+                }
+                None => last.push(c),
+            }
+        }
+        segments.push(Segment::new(&last, self.delay.clone()));
+        Segment::merge(segments)
+    }
+
+    /// Join neighboring segments with the same delay and remove empty ones.
+    ///
+    /// ```
+    /// # use shortcut_autotyper::Segment;
+    /// let segments = Segment::merge(vec![
+    ///     Segment::new("a", Some(10.into())),
+    ///     Segment::new("b", Some(10.into())),
+    ///     Segment::new("", None),
+    ///     Segment::new("c", None),
+    /// ]);
+    /// assert_eq!(segments, vec![Segment::new("ab", Some(10.into())), Segment::new("c", None)]);
+    /// ```
+    pub fn merge(segments: Vec<Segment>) -> Vec<Segment> {
+        segments
+            .into_iter()
+            .filter(|segment| !segment.text.is_empty())
+            .fold(Vec::new(), |mut merged: Vec<Segment>, segment| {
+                match merged.last_mut() {
+                    Some(last) if last.delay == segment.delay => last.text += &segment.text,
+                    _ => merged.push(segment),
+                }
+                merged
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split() {
+        let char_delays =
+            IndexMap::from([('\n', Delay::Fixed(250)), ('\t', Delay::Range(50..100))]);
+        assert_eq!(
+            Segment::new("abc", Some(Delay::Fixed(10))).split(&char_delays),
+            vec![Segment::new("abc", Some(Delay::Fixed(10)))]
+        );
+        assert_eq!(
+            Segment::new("\ta\n\nb\n", Some(Delay::Fixed(10))).split(&char_delays),
+            vec![
+                Segment::new("\t", Some(Delay::Range(50..100))),
+                Segment::new("a", Some(Delay::Fixed(10))),
+                Segment::new("\n\n", Some(Delay::Fixed(250))),
+                Segment::new("b", Some(Delay::Fixed(10))),
+                Segment::new("\n", Some(Delay::Fixed(250))),
+            ]
+        );
+        assert_eq!(Segment::new("", None).split(&char_delays), Vec::new());
+    }
+
+    #[test]
+    fn merge() {
+        assert_eq!(Segment::merge(Vec::new()), Vec::new());
+        assert_eq!(
+            Segment::merge(vec![
+                Segment::new("a", None),
+                Segment::new("b", Some(Delay::Fixed(10))),
+                Segment::new("", Some(Delay::Fixed(20))),
+                Segment::new("c", Some(Delay::Fixed(10))),
+                Segment::new("d", None),
+                Segment::new("e", None),
+            ]),
+            vec![
+                Segment::new("a", None),
+                Segment::new("bc", Some(Delay::Fixed(10))),
+                Segment::new("de", None),
+            ]
+        );
+    }
+}
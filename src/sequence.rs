@@ -1,17 +1,102 @@
 use crate::{
     command::Command,
     content::Content,
+    delay::Delay,
     error::{ATResult, ATVecResult, ErrType},
+    schema,
+    secret::{Secret, MASK},
 };
+use indexmap::IndexMap;
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
 use serde::{Deserialize, Serialize};
-use std::{
-    collections::{hash_map::Keys, HashMap},
-    str::FromStr,
-};
+use std::str::FromStr;
+
+/// Single sequence. It can be defined only by text, as a structure
+/// with text and typing settings or as a secret fetched from provider.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+enum Sequence {
+    Text(String),
+    Full {
+        /// Text of the sequence.
+        text: String,
+        /// Delay between key strokes in milliseconds.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        delay: Option<Delay>,
+        /// Time to wait in milliseconds before typing starts.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        start_delay: Option<usize>,
+        /// Hide the sequence in listings and cheat sheets.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        hidden: bool,
+    },
+    Secret {
+        /// Provider of the secret text, which is never printed.
+        secret: Secret,
+        /// Delay between key strokes in milliseconds.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        delay: Option<Delay>,
+        /// Time to wait in milliseconds before typing starts.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        start_delay: Option<usize>,
+        /// Hide the sequence in listings and cheat sheets.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        hidden: bool,
+    },
+}
+
+impl Sequence {
+    /// Returns text of the sequence, or [`MASK`] if it is secret.
+    fn text(&self) -> &str {
+        match self {
+            Sequence::Text(text) | Sequence::Full { text, .. } => text,
+            Sequence::Secret { .. } => MASK,
+        }
+    }
+
+    fn delay(&self) -> Option<&Delay> {
+        match self {
+            Sequence::Text(_) => None,
+            Sequence::Full { delay, .. } | Sequence::Secret { delay, .. } => delay.as_ref(),
+        }
+    }
+
+    fn start_delay(&self) -> Option<usize> {
+        match self {
+            Sequence::Text(_) => None,
+            Sequence::Full { start_delay, .. } | Sequence::Secret { start_delay, .. } => {
+                *start_delay
+            }
+        }
+    }
+
+    fn hidden(&self) -> bool {
+        match self {
+            Sequence::Text(_) => false,
+            Sequence::Full { hidden, .. } | Sequence::Secret { hidden, .. } => *hidden,
+        }
+    }
+}
+
+impl From<&str> for Sequence {
+    fn from(value: &str) -> Self {
+        Sequence::Text(String::from(value))
+    }
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Debug, PartialEq, Eq)]
+/// Structure for collection of defined sequences in order of insertion.
+pub struct Sequences(IndexMap<String, Sequence>);
 
-#[derive(Deserialize, Serialize, Default, Debug, PartialEq, Eq)]
-/// Structure for collection of defined sequences.
-pub struct Sequences(HashMap<String, String>);
+impl JsonSchema for Sequences {
+    fn schema_name() -> String {
+        String::from("Sequences")
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        schema::names_map::<Sequence>(gen)
+    }
+}
 
 impl Sequences {
     /// Create new instance of [`Sequences`] if identification name is valid
@@ -28,7 +113,7 @@ impl Sequences {
     /// assert_eq!(seq, Err(ErrType::InvalidKeyFormat(String::from("~")).into()));
     /// ```
     pub fn new(sequences: &[(&str, &str)]) -> ATResult<Sequences> {
-        let mut seq = Sequences(HashMap::new());
+        let mut seq = Sequences(IndexMap::new());
         for (key, value) in sequences.iter() {
             seq.insert(key, value)?;
         }
@@ -45,30 +130,59 @@ impl Sequences {
     /// assert_eq!(seq.get_sequence("A3", &Vec::new()).unwrap(), String::from("seq a,seq a,seq a,"));
     /// ```
     pub fn get_sequence(&self, key: &str, args: &[String]) -> ATResult<String> {
-        let command = Command::from_str(key)?;
-        match self.0.get(command.get_name()) {
-            Some(s) => Ok(Content::from(s.as_str())
-                .generate_content(args)
-                .repeat(command.get_times())),
-            None => ErrType::SequenceNotExist(String::from(command.get_name())).into(),
-        }
+        self.get_sequence_cmd(&Command::from_str(key)?, args)
     }
 
     /// Generate sequence from given [`Command`]. Returns string with generated
     /// sequence or error if sequence does not constraint value with command name.
+    /// Secret sequences are replaced by [`MASK`].
     pub fn get_sequence_cmd(&self, command: &Command, args: &[String]) -> ATResult<String> {
-        match self.0.get(command.get_name()) {
-            Some(s) => Ok(Content::from(s.as_str())
+        match self.get(command.get_name()) {
+            Some(s) => Ok(Content::from(s)
                 .generate_content(args)
                 .repeat(command.get_times())),
             None => ErrType::SequenceNotExist(String::from(command.get_name())).into(),
         }
     }
 
+    /// Generate text typed for given [`Command`]. Works same as
+    /// [`Sequences::get_sequence_cmd()`], only secret sequences are fetched
+    /// from their provider and typed without replacing placeholders.
+    pub fn reveal_sequence_cmd(&self, command: &Command, args: &[String]) -> ATResult<String> {
+        match self.0.get(command.get_name()) {
+            Some(Sequence::Secret { secret, .. }) => Ok(secret
+                .reveal(command.get_name())?
+                .repeat(command.get_times())),
+            _ => self.get_sequence_cmd(command, args),
+        }
+    }
+
     /// Returns a reference to the value corresponding to the key.
-    /// If value does not exists. Then returns [`None`].
-    pub fn get(&self, key: &str) -> Option<&String> {
-        self.0.get(key)
+    /// If value does not exists. Then returns [`None`]. Text of secret
+    /// sequences is [`MASK`].
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(Sequence::text)
+    }
+
+    /// Returns `true` if sequence with given `key` is secret.
+    pub fn is_secret(&self, key: &str) -> bool {
+        matches!(self.0.get(key), Some(Sequence::Secret { .. }))
+    }
+
+    /// Returns `true` if sequence with given `key` is hidden in listings.
+    pub fn is_hidden(&self, key: &str) -> bool {
+        self.0.get(key).is_some_and(Sequence::hidden)
+    }
+
+    /// Returns delay between key strokes of sequence with given `key`.
+    pub fn get_delay(&self, key: &str) -> Option<&Delay> {
+        self.0.get(key)?.delay()
+    }
+
+    /// Returns time in milliseconds to wait before typing of sequence
+    /// with given `key` starts.
+    pub fn get_start_delay(&self, key: &str) -> Option<usize> {
+        self.0.get(key)?.start_delay()
     }
 
     /// Find all keys invalid and returns errors caused by them
@@ -92,19 +206,46 @@ impl Sequences {
         match self.0.get(key) {
             Some(_) => ErrType::KeyIsInSequences(String::from(key)).into(),
             None => {
-                self.0.insert(String::from(key), String::from(value));
+                self.0.insert(String::from(key), Sequence::from(value));
                 Ok(())
             }
         }
     }
 
+    /// Remove sequence with given `key`. Returns `true` if it was present.
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.0.shift_remove(key).is_some()
+    }
+
+    /// Rename sequence `key` to `new_key` keeping its position.
+    /// Returns `true` if it was present.
+    pub fn rename(&mut self, key: &str, new_key: &str) -> bool {
+        match self.0.shift_remove_full(key) {
+            Some((index, _, sequence)) => {
+                self.0.shift_insert(index, String::from(new_key), sequence);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move all sequences from `other` to `self`. Sequences with the same
+    /// key are replaced by sequences from `other` keeping their position.
+    pub fn merge(&mut self, other: Sequences) {
+        self.0.extend(other.0);
+    }
+
+    /// Returns `true` if there are no sequences.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     /// Retrieves an iterator over the sequences.
     ///
     /// # Return Value
     ///
-    /// An iterator of type `Keys<'_, String, String>`
-    /// over the keys of the sequences.
-    pub fn get_keys(&self) -> Keys<'_, String, String> {
+    /// An iterator over the keys of the sequences.
+    pub fn get_keys(&self) -> impl Iterator<Item = &String> {
         self.0.keys()
     }
 }
@@ -197,12 +338,12 @@ mod tests {
     fn get_errors() {
         assert_eq!(example_sequences().get_errors(), Ok(()));
 
-        let mut seq = HashMap::new();
-        seq.insert(String::from(""), String::from(""));
-        seq.insert(String::from("1"), String::from(""));
-        seq.insert(String::from("A4"), String::from(""));
-        seq.insert(String::from("/A"), String::from(""));
-        seq.insert(String::from("B A"), String::from(""));
+        let mut seq = IndexMap::new();
+        seq.insert(String::from(""), Sequence::from(""));
+        seq.insert(String::from("1"), Sequence::from(""));
+        seq.insert(String::from("A4"), Sequence::from(""));
+        seq.insert(String::from("/A"), Sequence::from(""));
+        seq.insert(String::from("B A"), Sequence::from(""));
         let seq = Sequences(seq);
         let errors = seq.get_errors().unwrap_err();
 
@@ -218,23 +359,49 @@ mod tests {
     fn is_valid() {
         assert!(example_sequences().is_valid());
 
-        let mut seq = HashMap::new();
-        seq.insert(String::from("1"), String::from(""));
+        let mut seq = IndexMap::new();
+        seq.insert(String::from("1"), Sequence::from(""));
         assert!(!Sequences(seq).is_valid());
 
-        let mut seq = HashMap::new();
-        seq.insert(String::from("A4"), String::from(""));
+        let mut seq = IndexMap::new();
+        seq.insert(String::from("A4"), Sequence::from(""));
         assert!(!Sequences(seq).is_valid());
 
-        let mut seq = HashMap::new();
-        seq.insert(String::from("/A"), String::from(""));
+        let mut seq = IndexMap::new();
+        seq.insert(String::from("/A"), Sequence::from(""));
         assert!(!Sequences(seq).is_valid());
 
-        let mut seq = HashMap::new();
-        seq.insert(String::from("B A"), String::from(""));
+        let mut seq = IndexMap::new();
+        seq.insert(String::from("B A"), Sequence::from(""));
         assert!(!Sequences(seq).is_valid());
     }
 
+    #[test]
+    fn delays() {
+        let seq = serde_json::from_str::<Sequences>(
+            r#"{
+                "A": "A1",
+                "B": {"text": "B1", "delay": 10},
+                "D": {"text": "D1", "delay": "30..90"},
+                "C": {"text": "C1", "start_delay": 200}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(seq.get("A"), Some("A1"));
+        assert_eq!(seq.get("B"), Some("B1"));
+        assert_eq!(
+            seq.get_sequence("B2", &Vec::new()),
+            Ok(String::from("B1B1"))
+        );
+        assert_eq!(seq.get_delay("A"), None);
+        assert_eq!(seq.get_delay("B"), Some(&Delay::Fixed(10)));
+        assert_eq!(seq.get_delay("C"), None);
+        assert_eq!(seq.get_delay("D"), Some(&Delay::Range(30..90)));
+        assert_eq!(seq.get_delay("E"), None);
+        assert_eq!(seq.get_start_delay("B"), None);
+        assert_eq!(seq.get_start_delay("C"), Some(200));
+    }
+
     #[test]
     fn de_serialization() {
         let seq = example_sequences();
@@ -1,27 +1,658 @@
-use std::{error::Error, process::Command};
+use crate::{
+    delay::Delay,
+    error::{ATResult, ErrAutoType, ErrType},
+};
+use clap::ValueEnum;
+use rand::{seq::IndexedRandom, Rng};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{
+    env::{split_paths, var, var_os},
+    error::Error,
+    ffi::{OsStr, OsString},
+    fmt::{self, Display},
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicBool, Ordering},
+    thread::sleep,
+    time::Duration,
+};
 
+const KEYBOARD_ROWS: [&str; 3] = ["qwertyuiop", "asdfghjkl", "zxcvbnm"];
+const CHILD_POLL: Duration = Duration::from_millis(10);
+
+mod typing_session;
+mod x11;
+
+pub use typing_session::TypingSession;
+pub use x11::X11;
+
+/// Typing of text, that is stopped as soon as `interrupt` is set.
 pub trait TypeText {
-    fn type_text<T: AsRef<str>>(text: T, delay: usize) -> Result<(), Box<dyn Error>>;
+    fn type_text<T: AsRef<str>>(
+        text: T,
+        delay: usize,
+        interrupt: &AtomicBool,
+    ) -> Result<(), Box<dyn Error>>;
 }
 
 pub struct XDoTool {}
 
+impl XDoTool {
+    /// Command calling `program` as xdotool, reading text from standard input.
+    fn command<P: AsRef<OsStr>>(program: P, delay: usize) -> Command {
+        let mut sys_comand = Command::new(program);
+        sys_comand.args(["type", "--delay", &delay.to_string(), "--file", "-"]);
+        sys_comand
+    }
+}
+
 impl TypeText for XDoTool {
-    fn type_text<T: AsRef<str>>(text: T, delay: usize) -> Result<(), Box<dyn Error>> {
-        let mut sys_comand = Command::new("xdotool");
-        sys_comand.args(["type", "--delay", &delay.to_string(), text.as_ref()]);
-        sys_comand.spawn()?;
-        Ok(())
+    fn type_text<T: AsRef<str>>(
+        text: T,
+        delay: usize,
+        interrupt: &AtomicBool,
+    ) -> Result<(), Box<dyn Error>> {
+        run(
+            Self::command("xdotool", delay),
+            Some(text.as_ref()),
+            interrupt,
+        )
     }
 }
 
 pub struct Wtype {}
 
+impl Wtype {
+    /// Command calling `program` as wtype, reading text from standard input.
+    fn command<P: AsRef<OsStr>>(program: P, delay: usize) -> Command {
+        let mut sys_comand = Command::new(program);
+        sys_comand.args(["-d", &delay.to_string(), "-"]);
+        sys_comand
+    }
+}
+
 impl TypeText for Wtype {
-    fn type_text<T: AsRef<str>>(text: T, delay: usize) -> Result<(), Box<dyn Error>> {
-        let mut sys_comand = Command::new("wtype");
-        sys_comand.args(["-d", &delay.to_string(), text.as_ref()]);
-        sys_comand.spawn()?;
+    fn type_text<T: AsRef<str>>(
+        text: T,
+        delay: usize,
+        interrupt: &AtomicBool,
+    ) -> Result<(), Box<dyn Error>> {
+        run(
+            Self::command("wtype", delay),
+            Some(text.as_ref()),
+            interrupt,
+        )
+    }
+}
+
+/// Run typer `command` and wait for it to finish. If `stdin` is set,
+/// it is written to standard input of the command. If `interrupt` is set
+/// before the command finishes, the command is killed.
+fn run(
+    mut command: Command,
+    stdin: Option<&str>,
+    interrupt: &AtomicBool,
+) -> Result<(), Box<dyn Error>> {
+    if stdin.is_some() {
+        command.stdin(Stdio::piped());
+    }
+    let program = command.get_program().to_string_lossy().into_owned();
+    let failed = |message: String| {
+        ErrAutoType::new_with_message(ErrType::TyperFailed(program.clone()), message)
+    };
+    let mut child = command.spawn().map_err(|e| failed(e.to_string()))?;
+    if let (Some(mut pipe), Some(text)) = (child.stdin.take(), stdin) {
+        pipe.write_all(text.as_bytes())
+            .map_err(|e| failed(e.to_string()))?;
+    }
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return match status.success() {
+                true => Ok(()),
+                false => Err(failed(status.to_string()))?,
+            };
+        }
+        if interrupt.load(Ordering::Relaxed) {
+            child.kill()?;
+            child.wait()?;
+            Err(failed(String::from("killed")))?
+        }
+        sleep(CHILD_POLL);
+    }
+}
+
+/// Typing backends that can be selected explicitly or by [`Session::select()`].
+#[derive(ValueEnum, Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    Xdotool,
+    Wtype,
+    X11,
+}
+
+impl Backend {
+    /// Name of the binary the backend is calling. Returns [`None`]
+    /// for backends built in the program.
+    pub fn binary(&self) -> Option<&'static str> {
+        match self {
+            Backend::Xdotool => Some("xdotool"),
+            Backend::Wtype => Some("wtype"),
+            Backend::X11 => None,
+        }
+    }
+
+    /// Check if backend is able to type into native windows of `session`.
+    pub fn supports(&self, session: &Session) -> bool {
+        match self {
+            Backend::Xdotool | Backend::X11 => session.is_x11(),
+            Backend::Wtype => session.is_wayland(),
+        }
+    }
+
+    /// Type `text` with the backend.
+    pub fn type_text<T: AsRef<str>>(
+        &self,
+        text: T,
+        delay: usize,
+        interrupt: &AtomicBool,
+    ) -> Result<(), Box<dyn Error>> {
+        match self {
+            Backend::Xdotool => XDoTool::type_text(text, delay, interrupt),
+            Backend::Wtype => Wtype::type_text(text, delay, interrupt),
+            Backend::X11 => X11::type_text(text, delay, interrupt),
+        }
+    }
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::X11 => write!(f, "x11"),
+            backend => write!(f, "{}", backend.binary().unwrap_or_default()),
+        }
+    }
+}
+
+/// Typer defined in configuration as a program with arguments template.
+/// Placeholders `{text}` and `{delay}` in arguments are replaced by typed
+/// text and delay. If `stdin` is set, text is also written to standard
+/// input of the program.
+///
+/// ```
+/// # use shortcut_autotyper::typer::CommandTemplate;
+/// let typer: CommandTemplate =
+///     serde_json::from_str(r#"{"program": "dotool", "args": ["type", "{text}"]}"#).unwrap();
+/// assert_eq!(typer.get_program(), "dotool");
+/// ```
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+pub struct CommandTemplate {
+    /// Program called to type text.
+    program: String,
+    /// Arguments of the program with `{text}` and `{delay}` placeholders.
+    #[serde(default)]
+    args: Vec<String>,
+    /// Write typed text to standard input of the program.
+    #[serde(default)]
+    stdin: bool,
+}
+
+impl CommandTemplate {
+    /// Create new [`CommandTemplate`] calling `program` with `args`.
+    pub fn new(program: &str, args: &[&str], stdin: bool) -> CommandTemplate {
+        CommandTemplate {
+            program: String::from(program),
+            args: args.iter().map(|arg| String::from(*arg)).collect(),
+            stdin,
+        }
+    }
+
+    /// Return reference to program name.
+    pub fn get_program(&self) -> &str {
+        &self.program
+    }
+
+    /// Returns `true` if typed text is passed in arguments, where it
+    /// is visible to other processes.
+    pub fn exposes_text(&self) -> bool {
+        self.args.iter().any(|arg| arg.contains("{text}"))
+    }
+
+    /// Replace placeholders in arguments with `text` and `delay`.
+    fn expand_args(&self, text: &str, delay: usize) -> Vec<String> {
+        let delay = delay.to_string();
+        self.args
+            .iter()
+            .map(|arg| arg.replace("{delay}", &delay).replace("{text}", text))
+            .collect()
+    }
+
+    /// Run program with expanded arguments and wait for it to finish.
+    pub fn type_text<T: AsRef<str>>(
+        &self,
+        text: T,
+        delay: usize,
+        interrupt: &AtomicBool,
+    ) -> Result<(), Box<dyn Error>> {
+        let text = text.as_ref();
+        let mut sys_comand = Command::new(&self.program);
+        sys_comand.args(self.expand_args(text, delay));
+        run(sys_comand, self.stdin.then_some(text), interrupt)
+    }
+}
+
+/// Typer used to type generated text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Typer {
+    Backend(Backend),
+    Custom(CommandTemplate),
+}
+
+impl Typer {
+    /// Type `text` with the selected typer.
+    pub fn type_text<T: AsRef<str>>(
+        &self,
+        text: T,
+        delay: usize,
+        interrupt: &AtomicBool,
+    ) -> Result<(), Box<dyn Error>> {
+        match self {
+            Typer::Backend(backend) => backend.type_text(text, delay, interrupt),
+            Typer::Custom(template) => template.type_text(text, delay, interrupt),
+        }
+    }
+
+    /// Returns `true` if typed text is visible to other processes
+    /// in arguments of the typer, see [`CommandTemplate::exposes_text()`].
+    /// Backends always read the text from standard input.
+    pub fn exposes_text(&self) -> bool {
+        match self {
+            Typer::Backend(_) => false,
+            Typer::Custom(template) => template.exposes_text(),
+        }
+    }
+
+    /// Finish typing of the whole text. Keycode remapped by [`Backend::X11`]
+    /// is restored, other typers have nothing to finish.
+    pub fn finish(&self) -> Result<(), Box<dyn Error>> {
+        match self {
+            Typer::Backend(Backend::X11) => X11::finish(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Press backspace key. Custom typers type backspace character.
+    pub fn backspace(&self, interrupt: &AtomicBool) -> Result<(), Box<dyn Error>> {
+        match self {
+            Typer::Backend(Backend::Xdotool) => {
+                let mut sys_comand = Command::new("xdotool");
+                sys_comand.args(["key", "BackSpace"]);
+                run(sys_comand, None, interrupt)
+            }
+            Typer::Backend(Backend::Wtype) => {
+                let mut sys_comand = Command::new("wtype");
+                sys_comand.args(["-k", "BackSpace"]);
+                run(sys_comand, None, interrupt)
+            }
+            typer => typer.type_text("\u{8}", 0, interrupt),
+        }
+    }
+
+    /// Type `text` character by character with `delay` sampled for every
+    /// key stroke. With probability `typos` (from 0 to 1) a neighboring key
+    /// is typed before the character and corrected by backspace. If `delay`
+    /// is fixed and there are no typos, whole text is typed at once.
+    /// The delay is waited also after the last character.
+    pub fn type_humanized(
+        &self,
+        text: &str,
+        delay: &Delay,
+        typos: f64,
+        interrupt: &AtomicBool,
+    ) -> Result<(), Box<dyn Error>> {
+        let typos = typos.clamp(0.0, 1.0);
+        let wait = |delay: &Delay| sleep(Duration::from_millis(delay.sample() as u64));
+        if delay.is_fixed() && typos == 0.0 {
+            self.type_text(text, delay.sample(), interrupt)?;
+            wait(delay);
+            return Ok(());
+        }
+        let mut rng = rand::rng();
+        for c in text.chars() {
+            if let Some(typo) = typo(c).filter(|_| rng.random_bool(typos)) {
+                self.type_text(typo.to_string(), 0, interrupt)?;
+                wait(delay);
+                self.backspace(interrupt)?;
+                wait(delay);
+            }
+            self.type_text(c.to_string(), 0, interrupt)?;
+            wait(delay);
+        }
         Ok(())
     }
 }
+
+/// Returns random neighbor of ASCII letter `c` on QWERTY keyboard
+/// with the same case. For other characters returns [`None`].
+fn typo(c: char) -> Option<char> {
+    let lower = c.to_ascii_lowercase();
+    let row = KEYBOARD_ROWS.iter().find(|row| row.contains(lower))?;
+    let keys = row.chars().collect::<Vec<_>>();
+    let i = keys.iter().position(|k| *k == lower)?;
+    let neighbors = [i.checked_sub(1), Some(i + 1)]
+        .into_iter()
+        .flatten()
+        .filter_map(|i| keys.get(i).copied())
+        .collect::<Vec<_>>();
+    let typo = *neighbors.choose(&mut rand::rng())?;
+    Some(if c.is_ascii_uppercase() {
+        typo.to_ascii_uppercase()
+    } else {
+        typo
+    })
+}
+
+/// Description of the graphical session used to pick a suitable [`Backend`].
+#[derive(Debug, Default, Clone)]
+pub struct Session {
+    session_type: Option<String>,
+    wayland_display: Option<String>,
+    display: Option<String>,
+    path: Option<OsString>,
+}
+
+impl Session {
+    /// Create [`Session`] from `XDG_SESSION_TYPE`, `WAYLAND_DISPLAY`,
+    /// `DISPLAY` and `PATH` environment variables.
+    pub fn from_env() -> Session {
+        let non_empty = |name| var(name).ok().filter(|value| !value.is_empty());
+        Session {
+            session_type: non_empty("XDG_SESSION_TYPE"),
+            wayland_display: non_empty("WAYLAND_DISPLAY"),
+            display: non_empty("DISPLAY"),
+            path: var_os("PATH"),
+        }
+    }
+
+    /// Returns `true` if the session is running on Wayland.
+    pub fn is_wayland(&self) -> bool {
+        match self.session_type.as_deref() {
+            Some("wayland") => true,
+            Some("x11") => false,
+            _ => self.wayland_display.is_some(),
+        }
+    }
+
+    /// Returns `true` if the session is running on X11. Wayland sessions
+    /// with `DISPLAY` set by XWayland are not considered as X11.
+    pub fn is_x11(&self) -> bool {
+        match self.session_type.as_deref() {
+            Some("x11") => true,
+            Some("wayland") => false,
+            _ => !self.is_wayland() && self.display.is_some(),
+        }
+    }
+
+    /// Find executable `name` in directories from `PATH`.
+    pub fn find_binary(&self, name: &str) -> Option<PathBuf> {
+        split_paths(self.path.as_ref()?)
+            .map(|dir| dir.join(name))
+            .find(|file| is_executable(file))
+    }
+
+    /// Walk through `chain` and return the first [`Backend`] supporting
+    /// the session with binary available in `PATH`. If there is no such
+    /// backend, returns [`ErrType::NoTyperAvailable`] with reasons why each
+    /// backend was rejected.
+    pub fn select(&self, chain: &[Backend]) -> ATResult<Backend> {
+        let mut tried = Vec::new();
+        for backend in chain {
+            if !backend.supports(self) {
+                tried.push(format!("{backend}: unsupported session ({})", self));
+            } else if backend
+                .binary()
+                .is_some_and(|binary| self.find_binary(binary).is_none())
+            {
+                tried.push(format!("{backend}: binary not found in PATH"));
+            } else {
+                return Ok(*backend);
+            }
+        }
+        ErrType::NoTyperAvailable(tried).into()
+    }
+}
+
+impl Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_wayland() {
+            write!(f, "wayland")
+        } else if self.is_x11() {
+            write!(f, "x11")
+        } else {
+            write!(f, "no graphical session")
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(file: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    file.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(file: &Path) -> bool {
+    file.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, sync::Arc, time::Instant};
+    use tempfile::TempDir;
+
+    fn bin_dir(binaries: &[&str]) -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for binary in binaries {
+            let file = dir.path().join(binary);
+            fs::write(&file, "#!/bin/sh\n").unwrap();
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&file, fs::Permissions::from_mode(0o755)).unwrap();
+            }
+        }
+        dir
+    }
+
+    fn session(session_type: Option<&str>, wayland: bool, x11: bool, path: &Path) -> Session {
+        Session {
+            session_type: session_type.map(String::from),
+            wayland_display: wayland.then(|| String::from("wayland-0")),
+            display: x11.then(|| String::from(":0")),
+            path: Some(path.as_os_str().to_owned()),
+        }
+    }
+
+    #[test]
+    fn expand_args() {
+        let template = CommandTemplate::new("dotool", &["-d", "{delay}", "type", "{text}"], false);
+        assert_eq!(
+            template.expand_args("some {delay} text", 20),
+            vec!["-d", "20", "type", "some {delay} text"]
+        );
+        let template = CommandTemplate::new("dotool", &["--text={text}!"], false);
+        assert_eq!(template.expand_args("abc", 20), vec!["--text=abc!"]);
+    }
+
+    #[test]
+    fn template_type_text() {
+        let dir = bin_dir(&[]);
+        let output = dir.path().join("output");
+        let path = output.to_str().unwrap();
+
+        let script = r#"printf "%s %s" "$1" "$2" > "$0""#;
+        CommandTemplate::new("sh", &["-c", script, path, "{delay}", "{text}"], false)
+            .type_text("-text", 20, &AtomicBool::new(false))
+            .unwrap();
+        assert_eq!(fs::read_to_string(&output).unwrap(), "20 -text");
+
+        CommandTemplate::new("sh", &["-c", r#"cat > "$0""#, path], true)
+            .type_text("from\nstdin", 20, &AtomicBool::new(false))
+            .unwrap();
+        assert_eq!(fs::read_to_string(&output).unwrap(), "from\nstdin");
+
+        assert!(CommandTemplate::new("sh", &["-c", "exit 1"], false)
+            .type_text("", 20, &AtomicBool::new(false))
+            .is_err());
+    }
+
+    #[test]
+    fn backends_read_stdin() {
+        let dir = bin_dir(&[]);
+        let stub = dir.path().join("stub");
+        let output = dir.path().join("output");
+        fs::write(
+            &stub,
+            format!(
+                "#!/bin/sh\nprintf '%s\\n' \"$@\" > '{0}.args'\ncat > '{0}'\n",
+                output.display()
+            ),
+        )
+        .unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let text = "--delay 0 -\n\"quoted\"";
+        let args_file = dir.path().join("output.args");
+        for (command, args) in [
+            (
+                XDoTool::command(&stub, 20),
+                "type\n--delay\n20\n--file\n-\n",
+            ),
+            (Wtype::command(&stub, 20), "-d\n20\n-\n"),
+        ] {
+            run(command, Some(text), &AtomicBool::new(false)).unwrap();
+            assert_eq!(fs::read_to_string(&output).unwrap(), text);
+            assert_eq!(fs::read_to_string(&args_file).unwrap(), args);
+        }
+    }
+
+    #[test]
+    fn run_interrupted() {
+        let interrupt = Arc::new(AtomicBool::new(false));
+        let setter = Arc::clone(&interrupt);
+        let start = Instant::now();
+        std::thread::spawn(move || {
+            sleep(Duration::from_millis(100));
+            setter.store(true, Ordering::Relaxed);
+        });
+        let mut sys_comand = Command::new("sleep");
+        sys_comand.arg("10");
+        assert!(run(sys_comand, None, &interrupt).is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn typo() {
+        for _ in 0..100 {
+            assert!(['w'].contains(&super::typo('q').unwrap()));
+            assert!(['S', 'F'].contains(&super::typo('D').unwrap()));
+            assert!(['n'].contains(&super::typo('m').unwrap()));
+        }
+        assert_eq!(super::typo('1'), None);
+        assert_eq!(super::typo(' '), None);
+        assert_eq!(super::typo('ž'), None);
+    }
+
+    #[test]
+    fn type_humanized() {
+        let dir = bin_dir(&[]);
+        let output = dir.path().join("output");
+        let path = output.to_str().unwrap();
+        let script = r#"printf "%s" "$1" >> "$0""#;
+        let typer = Typer::Custom(CommandTemplate::new(
+            "sh",
+            &["-c", script, path, "{text}"],
+            false,
+        ));
+
+        typer
+            .type_humanized("Ab 1", &Delay::Range(0..2), 0.0, &AtomicBool::new(false))
+            .unwrap();
+        assert_eq!(fs::read_to_string(&output).unwrap(), "Ab 1");
+        fs::remove_file(&output).unwrap();
+
+        typer
+            .type_humanized("Ab 1", &Delay::Fixed(0), 1.0, &AtomicBool::new(false))
+            .unwrap();
+        let typed = fs::read_to_string(&output)
+            .unwrap()
+            .chars()
+            .collect::<Vec<_>>();
+        assert_eq!(typed.len(), 8);
+        assert_eq!(typed[0], 'S');
+        assert_eq!(&typed[1..3], &['\u{8}', 'A']);
+        assert!(['v', 'n'].contains(&typed[3]));
+        assert_eq!(&typed[4..], &['\u{8}', 'b', ' ', '1']);
+    }
+
+    #[test]
+    fn session_type() {
+        let path = PathBuf::new();
+        assert!(session(Some("wayland"), false, true, &path).is_wayland());
+        assert!(!session(Some("wayland"), false, true, &path).is_x11());
+        assert!(session(Some("x11"), true, true, &path).is_x11());
+        assert!(!session(Some("x11"), true, true, &path).is_wayland());
+        assert!(session(None, true, true, &path).is_wayland());
+        assert!(!session(None, true, true, &path).is_x11());
+        assert!(session(Some("tty"), false, true, &path).is_x11());
+        assert!(!session(None, false, false, &path).is_x11());
+        assert!(!session(None, false, false, &path).is_wayland());
+    }
+
+    #[test]
+    fn select() {
+        let chain = [Backend::Wtype, Backend::Xdotool];
+        let both = bin_dir(&["wtype", "xdotool"]);
+        let xdotool = bin_dir(&["xdotool"]);
+
+        assert_eq!(
+            session(Some("wayland"), true, true, both.path()).select(&chain),
+            Ok(Backend::Wtype)
+        );
+        assert_eq!(
+            session(Some("x11"), false, true, both.path()).select(&chain),
+            Ok(Backend::Xdotool)
+        );
+        assert_eq!(
+            session(Some("x11"), false, true, xdotool.path()).select(&chain),
+            Ok(Backend::Xdotool)
+        );
+        assert_eq!(
+            session(Some("wayland"), true, true, xdotool.path()).select(&chain),
+            ErrType::NoTyperAvailable(vec![
+                String::from("wtype: binary not found in PATH"),
+                String::from("xdotool: unsupported session (wayland)"),
+            ])
+            .into()
+        );
+        assert_eq!(
+            session(Some("x11"), false, true, xdotool.path())
+                .select(&[Backend::Wtype, Backend::X11]),
+            Ok(Backend::X11)
+        );
+        assert_eq!(
+            session(None, false, false, both.path()).select(&[Backend::Xdotool]),
+            ErrType::NoTyperAvailable(vec![String::from(
+                "xdotool: unsupported session (no graphical session)"
+            )])
+            .into()
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use super::Typer;
+use crate::{
+    delay::Delay,
+    error::{ErrAutoType, ErrType},
+    lock::{terminate, OnBusy, SessionLock},
+    segment::Segment,
+};
+use signal_hook::{
+    consts::{SIGINT, SIGTERM},
+    flag,
+};
+use std::{
+    error::Error,
+    io,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+const WAIT_POLL: Duration = Duration::from_millis(10);
+const LOCK_POLL: Duration = Duration::from_millis(50);
+
+/// Typing of [`Segment`]s split to chunks of limited size. Typing can be
+/// interrupted between chunks and running typer is killed if interruption
+/// comes in the middle of a chunk.
+pub struct TypingSession {
+    typer: Typer,
+    segments: Vec<Segment>,
+    chunk_size: usize,
+    interrupt: Arc<AtomicBool>,
+    typed: usize,
+    total: usize,
+    lock: Option<SessionLock>,
+}
+
+impl TypingSession {
+    /// Create new [`TypingSession`] typing `segments` with `typer`
+    /// in chunks of at most `chunk_size` characters.
+    pub fn new(typer: Typer, segments: Vec<Segment>, chunk_size: usize) -> TypingSession {
+        TypingSession {
+            typer,
+            total: segments
+                .iter()
+                .map(|segment| segment.get_text().chars().count())
+                .sum(),
+            segments,
+            chunk_size: chunk_size.max(1),
+            interrupt: Arc::new(AtomicBool::new(false)),
+            typed: 0,
+            lock: None,
+        }
+    }
+
+    /// Interrupt the session on `SIGINT` and `SIGTERM`. The second
+    /// signal terminates the program immediately.
+    pub fn handle_signals(&self) -> io::Result<()> {
+        for signal in [SIGINT, SIGTERM] {
+            flag::register_conditional_shutdown(signal, 1, Arc::clone(&self.interrupt))?;
+            flag::register(signal, Arc::clone(&self.interrupt))?;
+        }
+        Ok(())
+    }
+
+    /// Returns flag interrupting the session when it is set.
+    pub fn get_interrupt(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.interrupt)
+    }
+
+    /// Returns number of characters typed so far.
+    pub fn get_typed(&self) -> usize {
+        self.typed
+    }
+
+    /// Returns number of characters in all segments.
+    pub fn get_total(&self) -> usize {
+        self.total
+    }
+
+    /// Acquire lock on `path`, so only one session types at a time. If
+    /// the lock is held by another session, `on_busy` decides whether to wait
+    /// for it, stop it, or return [`ErrType::SessionRunning`]. The lock
+    /// is released when the session is dropped.
+    pub fn acquire_lock(&mut self, path: &Path, on_busy: OnBusy) -> Result<(), Box<dyn Error>> {
+        let mut terminated = None;
+        loop {
+            if let Some(lock) = SessionLock::try_acquire(path)? {
+                self.lock = Some(lock);
+                return Ok(());
+            }
+            if let Some(pid) = SessionLock::holder(path)? {
+                match on_busy {
+                    OnBusy::Refuse => Err(ErrAutoType::new(ErrType::SessionRunning(pid)))?,
+                    OnBusy::Replace if terminated != Some(pid) => {
+                        terminate(pid)?;
+                        terminated = Some(pid);
+                    }
+                    _ => {}
+                }
+            }
+            self.check_interrupt()?;
+            sleep(LOCK_POLL);
+        }
+    }
+
+    /// Wait for `duration` before typing starts.
+    pub fn wait(&self, duration: Duration) -> Result<(), ErrAutoType> {
+        let start = Instant::now();
+        while start.elapsed() < duration {
+            self.check_interrupt()?;
+            sleep(WAIT_POLL.min(duration - start.elapsed()));
+        }
+        self.check_interrupt()
+    }
+
+    /// Type all segments. Segments without delay are typed with `delay`.
+    /// See [`Typer::type_humanized()`] for description of `typos`.
+    /// If the session is interrupted, returns [`ErrType::Interrupted`]
+    /// with number of typed characters.
+    pub fn type_segments(&mut self, delay: &Delay, typos: f64) -> Result<(), Box<dyn Error>> {
+        let segments = std::mem::take(&mut self.segments);
+        let result = segments.iter().try_for_each(|segment| {
+            let delay = segment.get_delay().unwrap_or(delay);
+            chunks(segment.get_text(), self.chunk_size).try_for_each(|chunk| {
+                self.check_interrupt()?;
+                if let Err(e) = self
+                    .typer
+                    .type_humanized(chunk, delay, typos, &self.interrupt)
+                {
+                    self.check_interrupt()?;
+                    return Err(e);
+                }
+                self.typed += chunk.chars().count();
+                Ok(())
+            })
+        });
+        self.segments = segments;
+        let finished = self.typer.finish();
+        result.and(finished)
+    }
+
+    fn check_interrupt(&self) -> Result<(), ErrAutoType> {
+        if self.interrupt.load(Ordering::Relaxed) {
+            ErrType::Interrupted(self.typed, self.total).into()
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Split `text` to chunks of at most `size` characters.
+fn chunks(text: &str, size: usize) -> impl Iterator<Item = &str> {
+    let mut bounds = text
+        .char_indices()
+        .step_by(size)
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    bounds.push(text.len());
+    (1..bounds.len()).map(move |i| &text[bounds[i - 1]..bounds[i]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::typer::CommandTemplate;
+    use std::fs;
+    use tempfile::{NamedTempFile, TempDir};
+
+    fn output_typer() -> (Typer, NamedTempFile) {
+        let output = NamedTempFile::new().unwrap();
+        let script = r#"printf "%s|" "$1" >> "$0"; sleep "$2""#;
+        let path = output.path().to_str().unwrap();
+        let typer = Typer::Custom(CommandTemplate::new(
+            "sh",
+            &["-c", script, path, "{text}", "{delay}"],
+            false,
+        ));
+        (typer, output)
+    }
+
+    #[test]
+    fn chunks() {
+        assert_eq!(
+            super::chunks("abcdefgh", 3).collect::<Vec<_>>(),
+            vec!["abc", "def", "gh"]
+        );
+        assert_eq!(
+            super::chunks("žšč🐧", 2).collect::<Vec<_>>(),
+            vec!["žš", "č🐧"]
+        );
+        assert_eq!(super::chunks("ab", 5).collect::<Vec<_>>(), vec!["ab"]);
+        assert_eq!(super::chunks("", 5).count(), 0);
+    }
+
+    #[test]
+    fn type_segments() {
+        let (typer, output) = output_typer();
+        let segments = vec![
+            Segment::new("abcdefgh", None),
+            Segment::new("ij", Some(Delay::Fixed(0))),
+        ];
+        let mut session = TypingSession::new(typer, segments, 3);
+        session.type_segments(&Delay::Fixed(0), 0.0).unwrap();
+        assert_eq!(fs::read_to_string(output).unwrap(), "abc|def|gh|ij|");
+        assert_eq!(session.get_typed(), 10);
+        assert_eq!(session.get_total(), 10);
+    }
+
+    #[test]
+    fn acquire_lock() {
+        let (typer, _) = output_typer();
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("session.lock");
+        let mut first = TypingSession::new(typer.clone(), Vec::new(), 1);
+        first.acquire_lock(&path, OnBusy::Refuse).unwrap();
+
+        let mut second = TypingSession::new(typer, vec![Segment::new("ab", None)], 1);
+        let err = second.acquire_lock(&path, OnBusy::Refuse).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ErrAutoType::new(ErrType::SessionRunning(std::process::id())).to_string()
+        );
+
+        let interrupt = second.get_interrupt();
+        std::thread::spawn(move || {
+            sleep(Duration::from_millis(200));
+            interrupt.store(true, Ordering::Relaxed);
+        });
+        let err = second.acquire_lock(&path, OnBusy::Queue).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            ErrAutoType::new(ErrType::Interrupted(0, 2)).to_string()
+        );
+
+        drop(first);
+        second.interrupt.store(false, Ordering::Relaxed);
+        second.acquire_lock(&path, OnBusy::Queue).unwrap();
+        assert_eq!(
+            SessionLock::holder(&path).unwrap(),
+            Some(std::process::id())
+        );
+        drop(second);
+        assert_eq!(SessionLock::holder(&path).unwrap(), None);
+    }
+
+    #[test]
+    fn interrupted() {
+        let (typer, output) = output_typer();
+        let segments = vec![Segment::new("abcdef", None)];
+        let mut session = TypingSession::new(typer, segments, 2);
+        let interrupt = session.get_interrupt();
+        let start = Instant::now();
+        std::thread::spawn(move || {
+            sleep(Duration::from_millis(1500));
+            interrupt.store(true, Ordering::Relaxed);
+        });
+        let err = session.type_segments(&Delay::Fixed(1), 0.0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error: Typing interrupted after 2 of 6 characters"
+        );
+        assert!(start.elapsed() < Duration::from_secs(3));
+        assert_eq!(session.get_typed(), 2);
+        assert_eq!(fs::read_to_string(&output).unwrap(), "ab|cd|");
+        assert_eq!(
+            session.wait(Duration::from_secs(10)),
+            ErrType::Interrupted(2, 6).into()
+        );
+    }
+}
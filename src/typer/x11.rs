@@ -0,0 +1,526 @@
+use super::TypeText;
+use crate::error::{ErrAutoType, ErrType};
+use std::{
+    cell::RefCell,
+    error::Error,
+    sync::atomic::{AtomicBool, Ordering},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        xproto::{
+            AtomEnum, ConnectionExt as _, Keycode, Keysym, Window, KEY_PRESS_EVENT,
+            KEY_RELEASE_EVENT,
+        },
+        xtest::ConnectionExt as _,
+    },
+    rust_connection::RustConnection,
+    CURRENT_TIME, NONE,
+};
+
+const NO_SYMBOL: Keysym = 0;
+const SHIFT_L: Keysym = 0xffe1;
+const FOCUS_POLL: Duration = Duration::from_millis(50);
+/// Time given to clients to translate key events of remapped keycode
+/// before its mapping is changed again.
+const REMAP_DELAY: Duration = Duration::from_millis(50);
+
+thread_local! {
+    /// Connection shared by all typing of the thread, so typing
+    /// character by character does not connect for every character.
+    static CONNECTION: RefCell<Option<X11>> = const { RefCell::new(None) };
+}
+
+/// Keyboard mapping of the X server, list of keysyms for every keycode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Keymap {
+    min_keycode: Keycode,
+    keysyms_per_keycode: u8,
+    keysyms: Vec<Keysym>,
+}
+
+impl Keymap {
+    /// Returns keysym for given character.
+    fn keysym(c: char) -> Keysym {
+        match c {
+            '\n' | '\r' => 0xff0d,
+            '\t' => 0xff09,
+            '\u{8}' => 0xff08,
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as Keysym,
+            c => 0x0100_0000 | c as Keysym,
+        }
+    }
+
+    /// Iterator over keycodes and their keysyms.
+    fn keycodes(&self) -> impl Iterator<Item = (Keycode, &[Keysym])> {
+        self.keysyms
+            .chunks(usize::from(self.keysyms_per_keycode.max(1)))
+            .enumerate()
+            .map(|(i, keysyms)| (self.min_keycode.saturating_add(i as u8), keysyms))
+    }
+
+    /// Find keycode generating `keysym` and whether shift must be held.
+    /// Only the first two levels are searched, other levels depend on
+    /// modifiers that are not handled.
+    fn find(&self, keysym: Keysym) -> Option<(Keycode, bool)> {
+        self.keycodes().find_map(|(keycode, keysyms)| {
+            keysyms
+                .iter()
+                .take(2)
+                .position(|k| *k == keysym)
+                .map(|level| (keycode, level == 1))
+        })
+    }
+
+    /// Find keycode without any keysym, that can be remapped to characters
+    /// missing in the current layout.
+    fn spare(&self) -> Option<Keycode> {
+        self.keycodes()
+            .filter(|(keycode, _)| *keycode != 0)
+            .find(|(_, keysyms)| keysyms.iter().all(|k| *k == NO_SYMBOL))
+            .map(|(keycode, _)| keycode)
+    }
+}
+
+/// Typer sending key events directly to the X server with the XTest
+/// extension. Characters missing in the current layout are typed by
+/// temporary remapping of a spare keycode.
+pub struct X11 {
+    conn: RustConnection,
+    root: Window,
+    keymap: Keymap,
+    shift: Option<Keycode>,
+    remapped: Option<Keycode>,
+    pending: bool,
+}
+
+impl X11 {
+    /// Connect to the X server on `display`, or on `DISPLAY` if it is [`None`].
+    pub fn connect(display: Option<&str>) -> Result<X11, Box<dyn Error>> {
+        let (conn, screen) = x11rb::connect(display)?;
+        conn.xtest_get_version(2, 2)?.reply()?;
+        let setup = conn.setup();
+        let root = setup.roots[screen].root;
+        let min_keycode = setup.min_keycode;
+        let count = setup.max_keycode - min_keycode + 1;
+        let mapping = conn.get_keyboard_mapping(min_keycode, count)?.reply()?;
+        let keymap = Keymap {
+            min_keycode,
+            keysyms_per_keycode: mapping.keysyms_per_keycode,
+            keysyms: mapping.keysyms,
+        };
+        Ok(X11 {
+            shift: keymap.find(SHIFT_L).map(|(keycode, _)| keycode),
+            conn,
+            root,
+            keymap,
+            remapped: None,
+            pending: false,
+        })
+    }
+
+    /// Returns currently active window. It is read from `_NET_ACTIVE_WINDOW`
+    /// if window manager supports it, otherwise window with input focus
+    /// is returned.
+    pub fn active_window(&self) -> Result<Window, Box<dyn Error>> {
+        let atom = self
+            .conn
+            .intern_atom(true, b"_NET_ACTIVE_WINDOW")?
+            .reply()?
+            .atom;
+        if atom != NONE {
+            let property = self
+                .conn
+                .get_property(false, self.root, atom, AtomEnum::WINDOW, 0, 1)?
+                .reply()?;
+            if let Some(window) = property.value32().and_then(|mut value| value.next()) {
+                return Ok(window);
+            }
+        }
+        Ok(self.conn.get_input_focus()?.reply()?.focus)
+    }
+
+    /// Wait until active window changes from `window`, which is usually
+    /// taken by [`X11::active_window()`] when the program starts. Returns
+    /// [`ErrType::FocusNotChanged`] if it does not change in `timeout`.
+    pub fn wait_for_focus_change(
+        &self,
+        window: Window,
+        timeout: Duration,
+    ) -> Result<(), Box<dyn Error>> {
+        let start = Instant::now();
+        while self.active_window()? == window {
+            if start.elapsed() >= timeout {
+                Err(ErrAutoType::new(ErrType::FocusNotChanged(
+                    timeout.as_millis() as usize,
+                )))?
+            }
+            sleep(FOCUS_POLL);
+        }
+        Ok(())
+    }
+
+    /// Type every character of `text` and wait `delay` milliseconds
+    /// after each of them. Typing stops when `interrupt` is set.
+    /// Remapped keycode is restored afterwards.
+    pub fn type_str(
+        &mut self,
+        text: &str,
+        delay: usize,
+        interrupt: &AtomicBool,
+    ) -> Result<(), Box<dyn Error>> {
+        let result = self.type_chars(text, delay, interrupt);
+        self.restore()?;
+        result
+    }
+
+    /// Type `text` same as [`X11::type_str()`], but keep remapped keycode,
+    /// so it can be reused by following text.
+    fn type_chars(
+        &mut self,
+        text: &str,
+        delay: usize,
+        interrupt: &AtomicBool,
+    ) -> Result<(), Box<dyn Error>> {
+        let total = text.chars().count();
+        text.chars().enumerate().try_for_each(|(typed, c)| {
+            if interrupt.load(Ordering::Relaxed) {
+                Err(ErrAutoType::new(ErrType::Interrupted(typed, total)))?
+            }
+            self.type_char(c)?;
+            sleep(Duration::from_millis(delay as u64));
+            Ok(())
+        })
+    }
+
+    /// Restore keycode remapped by typing with the connection shared
+    /// by [`TypeText::type_text()`]. It should be called once, when
+    /// the whole text is typed.
+    pub fn finish() -> Result<(), Box<dyn Error>> {
+        CONNECTION.with_borrow_mut(|connection| match connection {
+            Some(x11) => x11.restore(),
+            None => Ok(()),
+        })
+    }
+
+    /// Press and release key generating `c`.
+    fn type_char(&mut self, c: char) -> Result<(), Box<dyn Error>> {
+        let keysym = Keymap::keysym(c);
+        let (keycode, shift) = match self.keymap.find(keysym) {
+            Some((keycode, false)) => (keycode, None),
+            Some((keycode, true)) if self.shift.is_some() => (keycode, self.shift),
+            _ => (self.remap(keysym)?, None),
+        };
+        self.pending |= Some(keycode) == self.remapped;
+        if let Some(shift) = shift {
+            self.fake_key(KEY_PRESS_EVENT, shift)?;
+        }
+        self.fake_key(KEY_PRESS_EVENT, keycode)?;
+        self.fake_key(KEY_RELEASE_EVENT, keycode)?;
+        if let Some(shift) = shift {
+            self.fake_key(KEY_RELEASE_EVENT, shift)?;
+        }
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    fn fake_key(&self, event: u8, keycode: Keycode) -> Result<(), Box<dyn Error>> {
+        self.conn
+            .xtest_fake_input(event, keycode, CURRENT_TIME, self.root, 0, 0, 0)?;
+        Ok(())
+    }
+
+    /// Map `keysym` to all levels of the spare keycode.
+    fn remap(&mut self, keysym: Keysym) -> Result<Keycode, Box<dyn Error>> {
+        let keycode = match self.remapped.or_else(|| self.keymap.spare()) {
+            Some(keycode) => keycode,
+            None => Err(ErrAutoType::new_with_message(
+                ErrType::TyperFailed(String::from("x11")),
+                format!("no spare keycode to type keysym {keysym:#x}"),
+            ))?,
+        };
+        self.set_mapping(keycode, keysym)?;
+        self.remapped = Some(keycode);
+        Ok(keycode)
+    }
+
+    /// Restore remapped keycode back to no symbol.
+    fn restore(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(keycode) = self.remapped.take() {
+            self.set_mapping(keycode, NO_SYMBOL)?;
+        }
+        Ok(())
+    }
+
+    /// Map `keycode` to `keysym`. If remapped keycode was typed since
+    /// the last change, clients get time to translate it first, otherwise
+    /// they could translate it by the new mapping.
+    fn set_mapping(&mut self, keycode: Keycode, keysym: Keysym) -> Result<(), Box<dyn Error>> {
+        if self.pending {
+            self.conn.get_input_focus()?.reply()?;
+            sleep(REMAP_DELAY);
+            self.pending = false;
+        }
+        let keysyms = vec![keysym; usize::from(self.keymap.keysyms_per_keycode)];
+        self.conn
+            .change_keyboard_mapping(1, keycode, self.keymap.keysyms_per_keycode, &keysyms)?;
+        self.conn.get_input_focus()?.reply()?;
+        Ok(())
+    }
+}
+
+impl TypeText for X11 {
+    fn type_text<T: AsRef<str>>(
+        text: T,
+        delay: usize,
+        interrupt: &AtomicBool,
+    ) -> Result<(), Box<dyn Error>> {
+        CONNECTION.with_borrow_mut(|connection| {
+            let x11 = match connection {
+                Some(x11) => x11,
+                None => connection.insert(X11::connect(None)?),
+            };
+            x11.type_chars(text.as_ref(), delay, interrupt)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::typer::Session;
+    use std::{
+        path::Path,
+        process::{Child, Command},
+    };
+    use x11rb::protocol::{
+        xproto::{CreateWindowAux, EventMask, InputFocus, WindowClass},
+        Event,
+    };
+
+    fn example_keymap() -> Keymap {
+        Keymap {
+            min_keycode: 8,
+            keysyms_per_keycode: 2,
+            keysyms: vec![
+                'a' as Keysym,
+                'A' as Keysym,
+                '1' as Keysym,
+                '!' as Keysym,
+                NO_SYMBOL,
+                NO_SYMBOL,
+                SHIFT_L,
+                NO_SYMBOL,
+            ],
+        }
+    }
+
+    #[test]
+    fn keysym() {
+        assert_eq!(Keymap::keysym('a'), 0x61);
+        assert_eq!(Keymap::keysym('~'), 0x7e);
+        assert_eq!(Keymap::keysym('é'), 0xe9);
+        assert_eq!(Keymap::keysym('\n'), 0xff0d);
+        assert_eq!(Keymap::keysym('\t'), 0xff09);
+        assert_eq!(Keymap::keysym('ž'), 0x0100_017e);
+        assert_eq!(Keymap::keysym('🐧'), 0x0101_f427);
+    }
+
+    #[test]
+    fn find() {
+        let keymap = example_keymap();
+        assert_eq!(keymap.find('a' as Keysym), Some((8, false)));
+        assert_eq!(keymap.find('A' as Keysym), Some((8, true)));
+        assert_eq!(keymap.find('!' as Keysym), Some((9, true)));
+        assert_eq!(keymap.find(SHIFT_L), Some((11, false)));
+        assert_eq!(keymap.find('b' as Keysym), None);
+    }
+
+    #[test]
+    fn spare() {
+        assert_eq!(example_keymap().spare(), Some(10));
+        let keymap = Keymap {
+            min_keycode: 8,
+            keysyms_per_keycode: 1,
+            keysyms: vec!['a' as Keysym],
+        };
+        assert_eq!(keymap.spare(), None);
+    }
+
+    struct Xvfb(Child);
+
+    impl Drop for Xvfb {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    /// Start Xvfb on `display` if it is installed. If it is not installed
+    /// and `SHORTCUT_AUTOTYPER_REQUIRE_XVFB` is set, the test fails.
+    fn start_xvfb(display: u32) -> Option<Xvfb> {
+        let Some(binary) = Session::from_env().find_binary("Xvfb") else {
+            assert!(
+                std::env::var_os("SHORTCUT_AUTOTYPER_REQUIRE_XVFB").is_none(),
+                "Xvfb is required but not installed"
+            );
+            eprintln!("Xvfb is not installed, skipping");
+            return None;
+        };
+        let xvfb = Xvfb(
+            Command::new(binary)
+                .args([format!(":{display}").as_str(), "-nolisten", "tcp"])
+                .spawn()
+                .ok()?,
+        );
+        let socket = format!("/tmp/.X11-unix/X{display}");
+        let start = Instant::now();
+        while !Path::new(&socket).exists() {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "Xvfb not started"
+            );
+            sleep(Duration::from_millis(50));
+        }
+        Some(xvfb)
+    }
+
+    /// Wait for next event of `conn`, fail if it does not come
+    /// before `deadline`.
+    fn next_event(conn: &RustConnection, deadline: Instant) -> Event {
+        loop {
+            if let Some(event) = conn.poll_for_event().unwrap() {
+                return event;
+            }
+            assert!(Instant::now() < deadline, "Timeout waiting for X11 event");
+            sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Translate keycode to character by `keymap` of recording client.
+    fn to_char(keymap: &Keymap, keycode: Keycode, shift: bool) -> Option<char> {
+        let (_, keysyms) = keymap.keycodes().find(|(k, _)| *k == keycode)?;
+        match keysyms[usize::from(shift)] {
+            0xff0d => Some('\n'),
+            keysym if keysym < 0x100 => char::from_u32(keysym),
+            _ => None,
+        }
+    }
+
+    /// Create window receiving key presses and focus it.
+    fn focused_window(conn: &RustConnection, root: Window) -> Window {
+        let window = conn.generate_id().unwrap();
+        let aux =
+            CreateWindowAux::new().event_mask(EventMask::KEY_PRESS | EventMask::STRUCTURE_NOTIFY);
+        conn.create_window(
+            0,
+            window,
+            root,
+            0,
+            0,
+            100,
+            100,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            0,
+            &aux,
+        )
+        .unwrap();
+        conn.map_window(window).unwrap();
+        conn.flush().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !matches!(next_event(conn, deadline), Event::MapNotify(_)) {}
+        conn.set_input_focus(InputFocus::PARENT, window, CURRENT_TIME)
+            .unwrap();
+        conn.get_input_focus().unwrap().reply().unwrap();
+        window
+    }
+
+    #[test]
+    fn xvfb_type_text() {
+        let display = 70 + std::process::id() % 100;
+        let Some(_xvfb) = start_xvfb(display) else {
+            return;
+        };
+        let display = format!(":{display}");
+
+        let (conn, screen) = x11rb::connect(Some(&display)).unwrap();
+        focused_window(&conn, conn.setup().roots[screen].root);
+
+        let mut typer = X11::connect(Some(&display)).unwrap();
+        let keymap = typer.keymap.clone();
+        let interrupt = AtomicBool::new(false);
+        typer.type_chars("Hello, ž", 0, &interrupt).unwrap();
+        typer.type_chars("World!\nž", 0, &interrupt).unwrap();
+        typer.restore().unwrap();
+        let spare = keymap.spare().unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut typed = String::new();
+        let mut remapped = 0;
+        while typed.len() < "Hello, World!\n".len() || remapped < 2 {
+            if let Event::KeyPress(event) = next_event(&conn, deadline) {
+                if event.detail == spare {
+                    remapped += 1;
+                } else if let Some(c) = to_char(&keymap, event.detail, event.state.bits() & 1 != 0)
+                {
+                    typed.push(c);
+                }
+            }
+        }
+        assert_eq!(typed, "Hello, World!\n");
+        assert_eq!(remapped, 2);
+        assert_eq!(
+            typer.remap(Keymap::keysym('ž')).unwrap(),
+            spare,
+            "remapped keycode is reused"
+        );
+        typer.restore().unwrap();
+
+        let err = typer.type_str("ab", 0, &AtomicBool::new(true)).unwrap_err();
+        assert_eq!(
+            *err.downcast::<ErrAutoType>().unwrap(),
+            ErrType::Interrupted(0, 2).into()
+        );
+
+        let mapping = conn
+            .get_keyboard_mapping(spare, 1)
+            .unwrap()
+            .reply()
+            .unwrap();
+        assert!(mapping.keysyms.iter().all(|k| *k == NO_SYMBOL));
+    }
+
+    #[test]
+    fn xvfb_wait_for_focus_change() {
+        let display = 170 + std::process::id() % 100;
+        let Some(_xvfb) = start_xvfb(display) else {
+            return;
+        };
+        let display = format!(":{display}");
+
+        let (conn, screen) = x11rb::connect(Some(&display)).unwrap();
+        let root = conn.setup().roots[screen].root;
+        let launcher = focused_window(&conn, root);
+        let typer = X11::connect(Some(&display)).unwrap();
+        assert_eq!(typer.active_window().unwrap(), launcher);
+        assert!(typer
+            .wait_for_focus_change(launcher, Duration::from_millis(200))
+            .is_err());
+
+        let focus = std::thread::spawn(move || {
+            sleep(Duration::from_millis(200));
+            focused_window(&conn, root)
+        });
+        typer
+            .wait_for_focus_change(launcher, Duration::from_secs(5))
+            .unwrap();
+        let target = focus.join().unwrap();
+        assert_eq!(typer.active_window().unwrap(), target);
+        typer
+            .wait_for_focus_change(launcher, Duration::from_millis(0))
+            .unwrap();
+    }
+}
@@ -0,0 +1,286 @@
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Output},
+};
+use tempfile::TempDir;
+
+/// Command running the program with `args` and configuration in `dir`.
+fn command(dir: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_shortcut-autotyper"));
+    command
+        .args(args)
+        .env("XDG_CONFIG_HOME", dir)
+        .env("XDG_CONFIG_DIRS", dir.join("system"))
+        .env("HOME", dir)
+        .env("XDG_RUNTIME_DIR", dir)
+        .env_remove("SHORTCUT_AUTOTYPER_CONFIG")
+        .env_remove("SHORTCUT_AUTOTYPER_PROFILE");
+    command
+}
+
+/// Run the program with `args` and configuration in `dir`.
+fn run(dir: &Path, args: &[&str]) -> Output {
+    command(dir, args).output().unwrap()
+}
+
+#[test]
+fn convert_keeps_includes() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("other.json"),
+        r#"{"sequences": {"B": "b"}}"#,
+    )
+    .unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(
+        &config,
+        r#"{"include": ["other.json"], "sequences": {"A": "a"}}"#,
+    )
+    .unwrap();
+    let output = run(
+        dir.path(),
+        &["convert", config.to_str().unwrap(), "--to", "yaml"],
+    );
+    assert!(output.status.success());
+    let converted = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        converted.contains("include:\n- other.json\n"),
+        "{converted}"
+    );
+    assert!(!converted.contains("B: b"), "{converted}");
+}
+
+#[test]
+fn missing_commands() {
+    let dir = TempDir::new().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{"sequences": {"A": "a"}}"#).unwrap();
+    let output = run(dir.path(), &["-c", config.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, "Error: Missing value for argument: COMMANDS\n");
+
+    let output = run(
+        dir.path(),
+        &["-c", config.to_str().unwrap(), "--error-format", "json"],
+    );
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(r#""type":"argument_missing","code":2"#),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn format_requires_list() {
+    let dir = TempDir::new().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{"sequences": {"A": "a"}}"#).unwrap();
+    let config = config.to_str().unwrap();
+    let output = run(dir.path(), &["-c", config, "--format", "tsv", "A"]);
+    assert_eq!(output.status.code(), Some(2));
+
+    for list in ["--list", "--list-full"] {
+        let output = run(dir.path(), &["-c", config, list, "--format", "tsv"]);
+        assert!(output.status.success(), "{output:?}");
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(
+            stdout,
+            "name\tkind\tdelay\thidden\targuments\texpansion\nA\tsequence\t\tfalse\t\ta\n"
+        );
+    }
+}
+
+#[test]
+fn add_references_other_layers() {
+    let dir = TempDir::new().unwrap();
+    let system = dir.path().join("system/shortcut-autotyper");
+    fs::create_dir_all(&system).unwrap();
+    fs::write(system.join("config.json"), r#"{"sequences": {"S": "s"}}"#).unwrap();
+    let user = dir.path().join("shortcut-autotyper");
+    fs::create_dir_all(&user).unwrap();
+    fs::write(user.join("other.json"), r#"{"sequences": {"I": "i"}}"#).unwrap();
+    let config = user.join("config.json");
+    fs::write(&config, r#"{"include": ["other.json"]}"#).unwrap();
+
+    let output = run(dir.path(), &["add", "X", "S I2", "--combination"]);
+    assert!(output.status.success(), "{output:?}");
+    let content = fs::read_to_string(&config).unwrap();
+    assert!(content.contains(r#""X""#), "{content}");
+    assert!(!content.contains(r#""S""#), "{content}");
+    assert!(!content.contains(r#""I""#), "{content}");
+    let output = run(dir.path(), &["add", "Y", "Z", "--combination"]);
+    assert_eq!(output.status.code(), Some(3), "{output:?}");
+}
+
+#[test]
+fn remove_reports_profiles() {
+    let dir = TempDir::new().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(
+        &config,
+        r#"{
+            "sequences": {"A": "a"},
+            "profiles": {"work": {"sequences": {"A": "w"}}, "home": {}}
+        }"#,
+    )
+    .unwrap();
+    let output = run(dir.path(), &["-c", config.to_str().unwrap(), "remove", "A"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Removed \"A\" also from profile \"work\"\n"
+    );
+    let content = fs::read_to_string(&config).unwrap();
+    assert!(!content.contains(r#""A""#), "{content}");
+}
+
+#[test]
+fn add_rejects_keys_of_other_layers() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("shared.json"),
+        r#"{"sequences": {"sig": "s"}}"#,
+    )
+    .unwrap();
+    let config = dir.path().join("config.json");
+    let content = r#"{"include": ["shared.json"], "sequences": {"Addr": "a"}}"#;
+    fs::write(&config, content).unwrap();
+    let config = config.to_str().unwrap();
+
+    for args in [
+        ["add", "sig", "foo"].as_slice(),
+        &["add", "sig", "Addr", "--combination"],
+    ] {
+        let output = run(dir.path(), &[["-c", config].as_slice(), args].concat());
+        assert_eq!(output.status.code(), Some(4), "{output:?}");
+    }
+    assert_eq!(fs::read_to_string(config).unwrap(), content);
+    assert!(run(dir.path(), &["-c", config, "--list"]).status.success());
+}
+
+#[test]
+fn rename_rejects_keys_of_other_layers() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("shared.json"),
+        r#"{"sequences": {"sig": "s"}}"#,
+    )
+    .unwrap();
+    let config = dir.path().join("config.json");
+    let content = r#"{"include": ["shared.json"], "sequences": {"Addr": "a"}}"#;
+    fs::write(&config, content).unwrap();
+    let config = config.to_str().unwrap();
+
+    let output = run(dir.path(), &["-c", config, "rename", "Addr", "sig"]);
+    assert_eq!(output.status.code(), Some(4), "{output:?}");
+    assert_eq!(fs::read_to_string(config).unwrap(), content);
+    assert!(run(dir.path(), &["-c", config, "--list"]).status.success());
+}
+
+#[test]
+fn import_renames_keys_of_other_layers() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("shared.json"),
+        r#"{"sequences": {"sig": "s"}}"#,
+    )
+    .unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(&config, r#"{"include": ["shared.json"]}"#).unwrap();
+    let config = config.to_str().unwrap();
+    let csv = dir.path().join("snippets.csv");
+    fs::write(&csv, "sig,imported\n").unwrap();
+
+    let output = run(dir.path(), &["-c", config, "import", csv.to_str().unwrap()]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "\"sig\" imported as \"sigA\"\nImported 1 sequences\n"
+    );
+    let output = run(dir.path(), &["-c", config, "--list-full"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "sig: s\nsigA: imported\n"
+    );
+}
+
+#[test]
+fn secret_not_in_typer_arguments() {
+    let dir = TempDir::new().unwrap();
+    let config = dir.path().join("shortcut-autotyper/config.json");
+    fs::create_dir_all(config.parent().unwrap()).unwrap();
+    fs::write(
+        &config,
+        r#"{
+            "sequences": {"A": "a", "T": {"secret": {"env": "SHORTCUT_AUTOTYPER_CLI_TOKEN"}}},
+            "combinations": {"X": {"sequence": "A T"}},
+            "typers": {"args": {"program": "true", "args": ["{text}"]}}
+        }"#,
+    )
+    .unwrap();
+    for key in ["T", "X", "A T2"] {
+        let output = command(dir.path(), &["--typer", "args", key])
+            .env("SHORTCUT_AUTOTYPER_CLI_TOKEN", "t0ken")
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(9), "{output:?}");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains("set \"stdin\" instead"), "{stderr}");
+        assert!(!stderr.contains("t0ken"), "{stderr}");
+    }
+}
+
+#[test]
+fn list_skips_hidden() {
+    let dir = TempDir::new().unwrap();
+    let config = dir.path().join("config.json");
+    fs::write(
+        &config,
+        r#"{
+            "sequences": {"A": "a", "H": {"text": "h", "hidden": true}},
+            "combinations": {"X": {"sequence": "A H"}, "Y": {"sequence": "H2", "hidden": true}}
+        }"#,
+    )
+    .unwrap();
+    let config = config.to_str().unwrap();
+    let output = run(dir.path(), &["-c", config, "--list-full"]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "A: a\nX: ah\n");
+    let output = run(dir.path(), &["-c", config, "--list", "--format", "tsv"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("H\tsequence\t\ttrue\t\th\n"), "{stdout}");
+    assert!(
+        stdout.contains("Y\tcombination\t\ttrue\t\tH2\n"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn type_key_named_as_subcommand() {
+    let dir = TempDir::new().unwrap();
+    let config = dir.path().join("config.json");
+    let output = dir.path().join("output");
+    fs::write(
+        &config,
+        format!(
+            r#"{{
+                "sequences": {{"stop": "s", "check": "c"}},
+                "typers": {{"file": {{"program": "sh", "args": ["-c", "cat >> {}"], "stdin": true}}}}
+            }}"#,
+            output.display()
+        ),
+    )
+    .unwrap();
+    let config = config.to_str().unwrap();
+    let listed = run(dir.path(), &["-c", config, "--list"]);
+    assert_eq!(String::from_utf8(listed.stdout).unwrap(), "check\nstop\n");
+
+    for key in ["stop", "check"] {
+        let typed = run(dir.path(), &["-c", config, "--typer", "file", "--", key]);
+        assert!(typed.status.success(), "{typed:?}");
+    }
+    assert_eq!(fs::read_to_string(output).unwrap(), "sc");
+}